@@ -1,14 +1,11 @@
 
 use crate::board::magic;
-#[allow(dead_code)]
-
 use crate::board::{
     color::Color,
     file::File,
     rank::Rank,
     square::SQ,
     bitboard::BB,
-    piece::PieceType,
 };
 
 pub fn pawn_attacks(sq: SQ, color: Color) -> BB {
@@ -35,10 +32,22 @@ pub fn king_attacks(sq: SQ) -> BB {
     KING_ATTACKS[sq]
 }
 
+/// Squares strictly between `a` and `b` if they share a rank, file or
+/// diagonal, otherwise empty.
+pub fn between(a: SQ, b: SQ) -> BB {
+    BETWEEN[a][b]
+}
+
+/// The full rank, file or diagonal through `a` and `b`, or empty if they
+/// are not aligned.
+pub fn line(a: SQ, b: SQ) -> BB {
+    LINE[a][b]
+}
+
 static PAWN_ATTACKS: [[BB; SQ::COUNT]; Color::COUNT] = {
     let mut attacks = [[BB(0); SQ::COUNT]; Color::COUNT];
     let mut sq_index = 0;
-    while sq_index < SQ::COUNT as usize {
+    while sq_index < SQ::COUNT {
         let sq = SQ::from_u8(sq_index as u8);
         attacks[Color::White.to_u8() as usize][sq_index] = generate_pawn_attacks(sq, Color::White);
         attacks[Color::Black.to_u8() as usize][sq_index] = generate_pawn_attacks(sq, Color::Black);
@@ -47,10 +56,10 @@ static PAWN_ATTACKS: [[BB; SQ::COUNT]; Color::COUNT] = {
     attacks
 };
 
-static KNIGHT_ATTACKS: [BB; SQ::COUNT as usize] = {
-    let mut attacks = [BB(0); SQ::COUNT as usize];
+static KNIGHT_ATTACKS: [BB; SQ::COUNT] = {
+    let mut attacks = [BB(0); SQ::COUNT];
     let mut sq_index = 0;
-    while sq_index < SQ::COUNT as usize {
+    while sq_index < SQ::COUNT {
         let sq = SQ::from_u8(sq_index as u8);
         attacks[sq_index] = generate_knight_attacks(sq);
         sq_index += 1;
@@ -58,10 +67,10 @@ static KNIGHT_ATTACKS: [BB; SQ::COUNT as usize] = {
     attacks
 };
 
-static KING_ATTACKS: [BB; SQ::COUNT as usize] = {
-    let mut attacks = [BB(0); SQ::COUNT as usize];
+static KING_ATTACKS: [BB; SQ::COUNT] = {
+    let mut attacks = [BB(0); SQ::COUNT];
     let mut sq_index = 0;
-    while sq_index < SQ::COUNT as usize {
+    while sq_index < SQ::COUNT {
         let sq = SQ::from_u8(sq_index as u8);
         attacks[sq_index] = generate_king_attacks(sq);
         sq_index += 1;
@@ -69,6 +78,91 @@ static KING_ATTACKS: [BB; SQ::COUNT as usize] = {
     attacks
 };
 
+static BETWEEN: [[BB; SQ::COUNT]; SQ::COUNT] = {
+    let mut table = [[BB(0); SQ::COUNT]; SQ::COUNT];
+    let mut a = 0;
+    while a < SQ::COUNT {
+        let mut b = 0;
+        while b < SQ::COUNT {
+            table[a][b] = generate_between(SQ::from_u8(a as u8), SQ::from_u8(b as u8));
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+static LINE: [[BB; SQ::COUNT]; SQ::COUNT] = {
+    let mut table = [[BB(0); SQ::COUNT]; SQ::COUNT];
+    let mut a = 0;
+    while a < SQ::COUNT {
+        let mut b = 0;
+        while b < SQ::COUNT {
+            table[a][b] = generate_line(SQ::from_u8(a as u8), SQ::from_u8(b as u8));
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+/// The unit step from `a` towards `b`, if the squares are distinct and aligned.
+const fn alignment(a: SQ, b: SQ) -> Option<(i8, i8)> {
+    let file_delta = b.file().to_u8() as i8 - a.file().to_u8() as i8;
+    let rank_delta = b.rank().to_u8() as i8 - a.rank().to_u8() as i8;
+
+    if file_delta == 0 && rank_delta == 0 {
+        return None;
+    }
+
+    if file_delta != 0 && rank_delta != 0 && file_delta.abs() != rank_delta.abs() {
+        return None;
+    }
+
+    Some((file_delta.signum(), rank_delta.signum()))
+}
+
+const fn generate_between(a: SQ, b: SQ) -> BB {
+    let mut between = BB(0);
+    let Some((file_step, rank_step)) = alignment(a, b) else {
+        return between;
+    };
+
+    let mut current_sq = a;
+    while let Some(sq) = current_sq.offset(file_step, rank_step) {
+        if sq.to_u8() == b.to_u8() {
+            break;
+        }
+        between.0 |= 1 << sq.to_u8();
+        current_sq = sq;
+    }
+
+    between
+}
+
+const fn generate_line(a: SQ, b: SQ) -> BB {
+    let mut line = BB(0);
+    let Some((file_step, rank_step)) = alignment(a, b) else {
+        return line;
+    };
+
+    line.0 |= 1 << a.to_u8();
+
+    let mut current_sq = a;
+    while let Some(sq) = current_sq.offset(file_step, rank_step) {
+        line.0 |= 1 << sq.to_u8();
+        current_sq = sq;
+    }
+
+    let mut current_sq = a;
+    while let Some(sq) = current_sq.offset(-file_step, -rank_step) {
+        line.0 |= 1 << sq.to_u8();
+        current_sq = sq;
+    }
+
+    line
+}
+
 const fn generate_pawn_attacks(sq: SQ, color: Color) -> BB {
     let mut attacks = BB(0);
     
@@ -86,14 +180,12 @@ const fn generate_pawn_attacks(sq: SQ, color: Color) -> BB {
         },
         Color::Black => {
             if sq.rank().to_u8() > Rank::First.to_u8() {
-                if sq.rank().to_u8() > Rank::Eighth.to_u8() {
-                    if sq.file().to_u8() > File::A.to_u8() {
-                        attacks.0 |= 1 << (sq.to_u8() as i8 - 9) as u8;
-                    }
-
-                    if sq.file().to_u8() < File::H.to_u8() {
-                        attacks.0 |= 1 << (sq.to_u8() as i8 - 7) as u8;
-                    }
+                if sq.file().to_u8() > File::A.to_u8() {
+                    attacks.0 |= 1 << (sq.to_u8() as i8 - 9) as u8;
+                }
+
+                if sq.file().to_u8() < File::H.to_u8() {
+                    attacks.0 |= 1 << (sq.to_u8() as i8 - 7) as u8;
                 }
             }
         }
@@ -119,10 +211,6 @@ const fn generate_knight_attacks(sq: SQ) -> BB {
     attacks
 }
 
-const fn generate_queen_attacks(sq: SQ, occupied: BB) -> BB {
-    BB(magic::generate_bishop_attacks(sq, occupied).0 | magic::generate_rook_attacks(sq, occupied).0)
-}
-
 const fn generate_king_attacks(sq: SQ) -> BB {
     let mut attacks = BB(0);
     let king_moves = [
@@ -141,9 +229,8 @@ const fn generate_king_attacks(sq: SQ) -> BB {
     attacks
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::board::attacks;
-
     use super::*;
 
     #[test]
@@ -152,14 +239,12 @@ mod tests {
         for sq in SQ::VARIANTS.iter().copied() {
             let mut attacks = BB::EMPTY;
 
-            if sq.rank() != Rank::First && sq.rank() != Rank::Eighth {
-                if let Some(sq1) = sq.offset(-1,1) {
-                    attacks |= BB(1) << sq1.to_u8();
-                }
+            if let Some(sq1) = sq.offset(-1,1) {
+                attacks |= BB(1) << sq1.to_u8();
+            }
 
-                if let Some(sq2) = sq.offset(1,1) {
-                    attacks |= BB(1) << sq2.to_u8();
-                }
+            if let Some(sq2) = sq.offset(1,1) {
+                attacks |= BB(1) << sq2.to_u8();
             }
 
             assert_eq!(pawn_attacks(sq, Color::White), attacks);
@@ -169,14 +254,12 @@ mod tests {
         for sq in SQ::VARIANTS.iter().copied() {
             let mut attacks = BB::EMPTY;
 
-            if sq.rank() != Rank::First && sq.rank() != Rank::Eighth {
-                if let Some(sq1) = sq.offset(-1,1) {
-                    attacks |= BB(1) << sq1.to_u8();
-                }
+            if let Some(sq1) = sq.offset(-1,-1) {
+                attacks |= BB(1) << sq1.to_u8();
+            }
 
-                if let Some(sq2) = sq.offset(1,1) {
-                    attacks |= BB(1) << sq2.to_u8();
-                }
+            if let Some(sq2) = sq.offset(1,-1) {
+                attacks |= BB(1) << sq2.to_u8();
             }
 
             assert_eq!(pawn_attacks(sq, Color::Black), attacks);
@@ -210,33 +293,48 @@ mod tests {
 
         let occupied = BB::from_sqs(&[SQ::D4]); // Place a piece on D4 to block attacks
 
-        // Test attacks from A1
-        let expected_attacks_a1 = BB::from_sqs(&[SQ::B2, SQ::C3]);
+        // Test attacks from A1 (the blocker itself is attacked)
+        let expected_attacks_a1 = BB::from_sqs(&[SQ::B2, SQ::C3, SQ::D4]);
         assert_eq!(bishop_attacks(SQ::A1, occupied), expected_attacks_a1);
 
         // Test attacks from H8
-        let expected_attacks_h8 = BB::from_sqs(&[SQ::G7, SQ::F6]);
+        let expected_attacks_h8 = BB::from_sqs(&[SQ::G7, SQ::F6, SQ::E5, SQ::D4]);
         assert_eq!(bishop_attacks(SQ::H8, occupied), expected_attacks_h8);
 
-        // Test attacks from D4 (should be blocked in all directions)
-        let expected_attacks_d4 = BB::EMPTY;
+        // Test attacks from D4 (a piece does not block itself)
+        let expected_attacks_d4 = BB::from_sqs(&[
+            SQ::A1, SQ::B2, SQ::C3, SQ::E5, SQ::F6, SQ::G7, SQ::H8,
+            SQ::A7, SQ::B6, SQ::C5, SQ::E3, SQ::F2, SQ::G1
+        ]);
         assert_eq!(bishop_attacks(SQ::D4, occupied), expected_attacks_d4);
+
+        // Test attacks from C5, hemmed in by blockers on every diagonal
+        let occupied = BB::from_sqs(&[SQ::B6, SQ::D6, SQ::B4, SQ::E3]);
+        let expected_attacks_c5 = BB::from_sqs(&[SQ::B6, SQ::D6, SQ::B4, SQ::D4, SQ::E3]);
+        assert_eq!(bishop_attacks(SQ::C5, occupied), expected_attacks_c5);
     }
 
     #[test]
     fn rook_attacks_test() {
         let occupied = BB::from_sqs(&[SQ::A5]);
 
-        // Test attacks from A1
-        let expected_attacks_a1 = BB::from_sqs(&[SQ::A2, SQ::A3, SQ::A4, SQ::B1, SQ::C1]);
+        // Test attacks from A1 (the blocker itself is attacked)
+        let expected_attacks_a1 = BB::from_sqs(&[
+            SQ::A2, SQ::A3, SQ::A4, SQ::A5,
+            SQ::B1, SQ::C1, SQ::D1, SQ::E1, SQ::F1, SQ::G1, SQ::H1
+        ]);
         assert_eq!(rook_attacks(SQ::A1, occupied), expected_attacks_a1);
 
-        // Test attacks from H8
-        let expected_attacks_h8 = BB::from_sqs(&[SQ::H7, SQ::H6, SQ::H5, SQ::G8, SQ::F8]);
-        assert_eq!(rook_attacks(SQ::H8, occupied), expected_attacks_h8);
+        // Test attacks from A8
+        let expected_attacks_a8 = BB::from_sqs(&[
+            SQ::A7, SQ::A6, SQ::A5,
+            SQ::B8, SQ::C8, SQ::D8, SQ::E8, SQ::F8, SQ::G8, SQ::H8
+        ]);
+        assert_eq!(rook_attacks(SQ::A8, occupied), expected_attacks_a8);
 
-        // Test attacks from D4 (should be blocked in all directions)
-        let expected_attacks_d4 = BB::EMPTY;
+        // Test attacks from D4, hemmed in by blockers on every line
+        let occupied = BB::from_sqs(&[SQ::D5, SQ::C4, SQ::F4, SQ::D2]);
+        let expected_attacks_d4 = BB::from_sqs(&[SQ::D5, SQ::C4, SQ::E4, SQ::F4, SQ::D3, SQ::D2]);
         assert_eq!(rook_attacks(SQ::D4, occupied), expected_attacks_d4);
     }
 
@@ -245,16 +343,36 @@ mod tests {
         let occupied = BB::from_sqs(&[SQ::D4]); 
 
         // Test attacks from A1
-        let expected_attacks_a1 = BB::from_sqs(&[SQ::A2, SQ::A3, SQ::A4, SQ::B1, SQ::C1, SQ::B2, SQ::C3]);
+        let expected_attacks_a1 = bishop_attacks(SQ::A1, occupied) | rook_attacks(SQ::A1, occupied);
         assert_eq!(queen_attacks(SQ::A1, occupied), expected_attacks_a1);
+        assert!(queen_attacks(SQ::A1, occupied).are_set(&[SQ::D4, SQ::A8, SQ::H1]));
+        assert!(!queen_attacks(SQ::A1, occupied).is_set(SQ::E5));
 
-        // Test attacks from H8
-        let expected_attacks_h8 = BB::from_sqs(&[SQ::H7, SQ::H6, SQ::H5, SQ::G8, SQ::F8, SQ::G7, SQ::F6]);
-        assert_eq!(queen_attacks(SQ::H8, occupied), expected_attacks_h8);
+        // Test attacks from D4 on an otherwise empty board
+        assert_eq!(queen_attacks(SQ::D4, occupied).count(), 27);
+    }
 
-        // Test attacks from D4 (should be blocked in all directions)
-        let expected_attacks_d4 = BB::EMPTY;
-        assert_eq!(queen_attacks(SQ::D4, occupied), expected_attacks_d4);
+    #[test]
+    fn between_test() {
+        assert_eq!(between(SQ::A1, SQ::D4), BB::from_sqs(&[SQ::B2, SQ::C3]));
+        assert_eq!(between(SQ::D4, SQ::A1), BB::from_sqs(&[SQ::B2, SQ::C3]));
+        assert_eq!(between(SQ::E1, SQ::E8), BB::from_sqs(&[SQ::E2, SQ::E3, SQ::E4, SQ::E5, SQ::E6, SQ::E7]));
+        assert_eq!(between(SQ::H3, SQ::E3), BB::from_sqs(&[SQ::G3, SQ::F3]));
+        assert_eq!(between(SQ::C2, SQ::C3), BB::EMPTY);
+        assert_eq!(between(SQ::B1, SQ::C3), BB::EMPTY);
+        assert_eq!(between(SQ::E4, SQ::E4), BB::EMPTY);
+    }
+
+    #[test]
+    fn line_test() {
+        assert_eq!(line(SQ::C3, SQ::E5), BB(0x8040201008040201));
+        assert_eq!(line(SQ::B2, SQ::B7), BB::from_file(File::B));
+        assert_eq!(line(SQ::A4, SQ::H4), BB::from_rank(Rank::Fourth));
+        assert_eq!(line(SQ::G1, SQ::A7), BB::from_sqs(&[
+            SQ::G1, SQ::F2, SQ::E3, SQ::D4, SQ::C5, SQ::B6, SQ::A7
+        ]));
+        assert_eq!(line(SQ::B1, SQ::C3), BB::EMPTY);
+        assert_eq!(line(SQ::E4, SQ::E4), BB::EMPTY);
     }
 
     #[test]
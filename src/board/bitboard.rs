@@ -61,7 +61,7 @@ impl BB {
                     print!(". ");
                 }
             }
-            print!("\n");
+            println!();
         }
     }
 
@@ -82,9 +82,9 @@ impl BB {
     };
 
     pub const RANKS: [BB; Rank::COUNT] = {
-        let mut ranks = [BB(0); Rank::COUNT as usize];
+        let mut ranks = [BB(0); Rank::COUNT];
         let mut i = 0;
-        while i < Rank::COUNT as usize {
+        while i < Rank::COUNT {
             ranks[i] = BB(0xFFu64 << (i * 8));
             i += 1;
         }
@@ -92,9 +92,9 @@ impl BB {
     };
 
     pub const SQUARES: [BB; SQ::COUNT] = {
-        let mut squares = [BB(0); SQ::COUNT as usize];
+        let mut squares = [BB(0); SQ::COUNT];
         let mut i = 0;
-        while i < SQ::COUNT as usize {
+        while i < SQ::COUNT {
             squares[i] = BB(1u64 << i);
             i += 1;
         }
@@ -241,10 +241,10 @@ mod tests {
     #[test]
     fn from_sqs() {
         assert_eq!(BB::from_sqs(&[]), BB::EMPTY);
-        assert_eq!(BB::from_sqs(&[SQ::A8]), BB(0x0000000000000080));
+        assert_eq!(BB::from_sqs(&[SQ::A8]), BB(0x0100000000000000));
         assert_eq!(BB::from_sqs(&[SQ::A1, SQ::A1]), BB(0x0000000000000001));
-        assert_eq!(BB::from_sqs(&[SQ::F6, SQ::C3, SQ::E5]), BB(0x0000000000280001));
-        assert_eq!(BB::from_sqs(&[SQ::A7, SQ::G5, SQ::B4, SQ::H2]), BB(0x8080000000000010));
+        assert_eq!(BB::from_sqs(&[SQ::F6, SQ::C3, SQ::E5]), BB(0x0000201000040000));
+        assert_eq!(BB::from_sqs(&[SQ::A7, SQ::G5, SQ::B4, SQ::H2]), BB(0x0001004002008000));
     }
 
     #[test]
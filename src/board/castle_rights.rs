@@ -1,118 +1,172 @@
-#[allow(dead_code)]
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Index, IndexMut, Not};
 
+use crate::board::Color;
+
+/// The wing a castle goes to: the king's side, towards the h-file, or the
+/// queen's side, towards the a-file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CastleRights {
-    None,
-    KingSide,
-    QueenSide,
-    Both        
+#[repr(u8)]
+pub enum CastleSide {
+    King,
+    Queen
+}
+
+impl CastleSide {
+    pub const COUNT: usize = 2;
+    pub const VARIANTS: [CastleSide; CastleSide::COUNT] = [
+        CastleSide::King,
+        CastleSide::Queen
+    ];
 }
 
+impl<T> Index<CastleSide> for [T] {
+    type Output = T;
+
+    fn index(&self, side: CastleSide) -> &Self::Output {
+        &self[side as usize]
+    }
+}
+
+impl<T> IndexMut<CastleSide> for [T] {
+    fn index_mut(&mut self, side: CastleSide) -> &mut Self::Output {
+        &mut self[side as usize]
+    }
+}
+
+/// The castle rights of both colors as a set of four flags: white
+/// kingside, white queenside, black kingside and black queenside, from the
+/// lowest bit up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CastleRights(u8);
+
 impl CastleRights {
-    pub fn has_kingside(self) -> bool {
-        if self == CastleRights::KingSide || self == CastleRights::Both {
-            true
-        } else {
-            false
-        }
+    pub const NONE: CastleRights = CastleRights(0);
+    pub const WHITE_KINGSIDE: CastleRights = CastleRights(1);
+    pub const WHITE_QUEENSIDE: CastleRights = CastleRights(2);
+    pub const BLACK_KINGSIDE: CastleRights = CastleRights(4);
+    pub const BLACK_QUEENSIDE: CastleRights = CastleRights(8);
+    pub const ALL: CastleRights = CastleRights(15);
+
+    /// The single right for `color` to castle towards `side`.
+    pub const fn new(color: Color, side: CastleSide) -> CastleRights {
+        CastleRights(1 << (2 * color as u8 + side as u8))
     }
 
-    pub fn has_queenside(self) -> bool {
-        if self == CastleRights::QueenSide || self == CastleRights::Both {
-            true
-        } else {
-            false
-        }
+    /// Both of `color`'s rights.
+    pub const fn both(color: Color) -> CastleRights {
+        CastleRights(3 << (2 * color as u8))
     }
 
-    pub fn remove_kingside(&mut self) {
-        *self = match *self {
-            CastleRights::KingSide => CastleRights::None,
-            CastleRights::Both     => CastleRights::QueenSide,
-            _ => *self,
-        }
+    /// The set with the flags in the low four bits of `value`.
+    pub const fn from_u8(value: u8) -> CastleRights {
+        CastleRights(value & CastleRights::ALL.0)
     }
 
-    pub fn remove_queenside(&mut self) {
-        *self = match *self {
-            CastleRights::QueenSide => CastleRights::None,
-            CastleRights::Both      => CastleRights::KingSide,
-            _ => *self,
-        }
+    pub const fn to_u8(self) -> u8 {
+        self.0
+    }
+
+    pub const fn has(self, color: Color, side: CastleSide) -> bool {
+        self.contains(CastleRights::new(color, side))
+    }
+
+    pub const fn contains(self, rights: CastleRights) -> bool {
+        self.0 & rights.0 == rights.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn insert(&mut self, rights: CastleRights) {
+        self.0 |= rights.0;
+    }
+
+    pub fn remove(&mut self, rights: CastleRights) {
+        self.0 &= !rights.0;
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;   
+impl BitAnd for CastleRights {
+    type Output = Self;
 
-    #[test]
-    fn has_kingside() {
-        let rights = CastleRights::Both;
-        assert!(rights.has_kingside());
+    fn bitand(self, rhs: Self) -> Self::Output {
+        CastleRights(self.0 & rhs.0)
+    }
+}
 
-        let rights = CastleRights::KingSide;
-        assert!(rights.has_kingside());
+impl BitAndAssign for CastleRights {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
 
-        let rights = CastleRights::QueenSide;
-        assert!(!rights.has_kingside());
+impl BitOr for CastleRights {
+    type Output = Self;
 
-        let rights = CastleRights::None;
-        assert!(!rights.has_kingside());
+    fn bitor(self, rhs: Self) -> Self::Output {
+        CastleRights(self.0 | rhs.0)
     }
+}
 
-    #[test]
-    fn has_queenside() {
-        let rights = CastleRights::Both;
-        assert!(rights.has_queenside());
+impl BitOrAssign for CastleRights {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
 
-        let rights = CastleRights::KingSide;
-        assert!(!rights.has_queenside());
+impl Not for CastleRights {
+    type Output = Self;
 
-        let rights = CastleRights::QueenSide;
-        assert!(rights.has_queenside());
-        
-        let rights = CastleRights::None;
-        assert!(!rights.has_queenside());
+    fn not(self) -> Self::Output {
+        CastleRights(!self.0 & CastleRights::ALL.0)
     }
-    
-    #[test]
-    fn remove_kingside() {
-        let mut rights = CastleRights::Both;
-        rights.remove_kingside();
-        assert_eq!(rights, CastleRights::QueenSide);
-        
-        rights = CastleRights::KingSide;
-        rights.remove_kingside();
-        assert_eq!(rights, CastleRights::None);
+}
 
-        rights = CastleRights::QueenSide;
-        rights.remove_kingside();
-        assert_eq!(rights, CastleRights::QueenSide);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        rights = CastleRights::None;
-        rights.remove_kingside();
-        assert_eq!(rights, CastleRights::None);
+    #[test]
+    fn new_and_both() {
+        assert_eq!(CastleRights::new(Color::White, CastleSide::King), CastleRights::WHITE_KINGSIDE);
+        assert_eq!(CastleRights::new(Color::White, CastleSide::Queen), CastleRights::WHITE_QUEENSIDE);
+        assert_eq!(CastleRights::new(Color::Black, CastleSide::King), CastleRights::BLACK_KINGSIDE);
+        assert_eq!(CastleRights::new(Color::Black, CastleSide::Queen), CastleRights::BLACK_QUEENSIDE);
+        assert_eq!(CastleRights::both(Color::White), CastleRights::WHITE_KINGSIDE | CastleRights::WHITE_QUEENSIDE);
+        assert_eq!(CastleRights::both(Color::White) | CastleRights::both(Color::Black), CastleRights::ALL);
     }
 
     #[test]
-    fn remove_queenside() {
-        let mut rights = CastleRights::Both;
-        rights.remove_queenside();
-        assert_eq!(rights, CastleRights::KingSide);
-        
-        rights = CastleRights::QueenSide;
-        rights.remove_queenside();
-        assert_eq!(rights, CastleRights::None);
-
-        rights = CastleRights::KingSide;
-        rights.remove_queenside();
-        assert_eq!(rights, CastleRights::KingSide);
-
-        rights = CastleRights::None;
-        rights.remove_queenside();
-        assert_eq!(rights, CastleRights::None);
+    fn has_and_contains() {
+        let rights = CastleRights::WHITE_QUEENSIDE | CastleRights::BLACK_KINGSIDE;
+        assert!(rights.has(Color::White, CastleSide::Queen));
+        assert!(rights.has(Color::Black, CastleSide::King));
+        assert!(!rights.has(Color::White, CastleSide::King));
+        assert!(!rights.has(Color::Black, CastleSide::Queen));
+        assert!(CastleRights::ALL.contains(rights));
+        assert!(!rights.contains(CastleRights::both(Color::White)));
+        assert!(CastleRights::NONE.is_empty());
     }
-}
 
+    #[test]
+    fn insert_and_remove() {
+        let mut rights = CastleRights::NONE;
+        rights.insert(CastleRights::BLACK_QUEENSIDE);
+        rights.insert(CastleRights::WHITE_KINGSIDE);
+        assert_eq!(rights.to_u8(), 0b1001);
+
+        rights.remove(CastleRights::both(Color::Black));
+        assert_eq!(rights, CastleRights::WHITE_KINGSIDE);
+        rights.remove(CastleRights::WHITE_KINGSIDE);
+        assert!(rights.is_empty());
+    }
 
+    #[test]
+    fn operators_stay_within_four_bits() {
+        assert_eq!(!CastleRights::NONE, CastleRights::ALL);
+        assert_eq!(!CastleRights::WHITE_KINGSIDE, CastleRights::from_u8(0b1110));
+        assert_eq!(CastleRights::from_u8(0xff), CastleRights::ALL);
+        assert_eq!(CastleRights::ALL & !CastleRights::both(Color::White), CastleRights::both(Color::Black));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::board::{Board, CastleSide, Color, File, Piece, PieceType};
+
+/// The squares the two knights take among the five left empty by the
+/// bishops and queen, in Scharnagl's order.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)
+];
+
+impl Board {
+    /// The number of Chess960 start positions.
+    pub const CHESS960_COUNT: u16 = 960;
+
+    /// The Chess960 start position numbered `index` in Scharnagl's scheme,
+    /// in which 518 is the standard start position, or `None` if `index` is
+    /// 960 or more. Castle rights are given by the rooks' files.
+    pub fn chess960(index: u16) -> Option<Board> {
+        if index >= Board::CHESS960_COUNT {
+            return None;
+        }
+
+        let mut back_rank = [None; File::COUNT];
+        let mut n = index as usize;
+        back_rank[2 * (n % 4) + 1] = Some(PieceType::Bishop);
+        n /= 4;
+        back_rank[2 * (n % 4)] = Some(PieceType::Bishop);
+        n /= 4;
+        place_on_empty(&mut back_rank, n % 6, PieceType::Queen);
+        n /= 6;
+
+        // The second knight goes first so the first one's square keeps its place.
+        let (first, second) = KNIGHT_PLACEMENTS[n];
+        place_on_empty(&mut back_rank, second, PieceType::Knight);
+        place_on_empty(&mut back_rank, first, PieceType::Knight);
+
+        // The king goes between the rooks on the three squares left.
+        place_on_empty(&mut back_rank, 0, PieceType::Rook);
+        place_on_empty(&mut back_rank, 0, PieceType::King);
+        place_on_empty(&mut back_rank, 0, PieceType::Rook);
+
+        let pieces: Vec<PieceType> = back_rank.into_iter().map(|piece| piece.expect("every file is filled")).collect();
+        let row = |color| pieces.iter().map(|&piece_type| Piece::new(color, piece_type).to_char()).collect::<String>();
+        let rook_files: String = File::VARIANTS
+            .into_iter()
+            .rev()
+            .filter(|&file| pieces[file as usize] == PieceType::Rook)
+            .map(File::to_char)
+            .collect();
+
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+            row(Color::Black), row(Color::White), rook_files.to_ascii_uppercase(), rook_files
+        );
+        Some(Board::from_fen(&fen).expect("Chess960 start positions are valid"))
+    }
+
+    /// Whether a castle allowed here can only be written as the king taking
+    /// its rook, because the king is off the e-file or the rook is off the
+    /// a- or h-file.
+    pub fn has_chess960_castling(&self) -> bool {
+        Color::VARIANTS.into_iter().any(|color| {
+            CastleSide::VARIANTS.into_iter().any(|side| {
+                let rook_file = match side {
+                    CastleSide::King  => File::H,
+                    CastleSide::Queen => File::A
+                };
+                self.castle_rights().has(color, side)
+                    && (self.king_sq(color).file() != File::E || self.castle_rook_sq(color, side).file() != rook_file)
+            })
+        })
+    }
+}
+
+/// Puts `piece_type` on the `nth` empty square of `back_rank`.
+fn place_on_empty(back_rank: &mut [Option<PieceType>; File::COUNT], nth: usize, piece_type: PieceType) {
+    let sq = back_rank
+        .iter_mut()
+        .filter(|sq| sq.is_none())
+        .nth(nth)
+        .expect("there are enough empty squares");
+    *sq = Some(piece_type);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{CastleRights, SQ};
+
+    #[test]
+    fn index_518_is_the_standard_start_position() {
+        let board = Board::chess960(518).unwrap();
+        assert_eq!(board, Board::from_fen(Board::START_FEN).unwrap());
+    }
+
+    #[test]
+    fn known_start_positions() {
+        let fen = |index| Board::chess960(index).unwrap().to_fen();
+        assert_eq!(fen(0), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(fen(959), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        assert_eq!(Board::chess960(960), None);
+
+        let board = Board::chess960(0).unwrap();
+        assert_eq!(board.castle_rights(), CastleRights::ALL);
+        assert_eq!(board.castle_rook_sq(Color::White, CastleSide::King), SQ::H1);
+        assert_eq!(board.castle_rook_sq(Color::Black, CastleSide::Queen), SQ::F8);
+    }
+
+    #[test]
+    fn chess960_castling() {
+        assert!(!Board::chess960(518).unwrap().has_chess960_castling());
+        assert!(Board::chess960(0).unwrap().has_chess960_castling());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/1R2K2R w B - 0 1").unwrap().has_chess960_castling());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/1R2K2R w K - 0 1").unwrap().has_chess960_castling());
+        assert!(!Board::from_fen("rk6/8/8/8/8/8/8/4K3 w - - 0 1").unwrap().has_chess960_castling());
+    }
+
+    #[test]
+    fn every_start_position_is_distinct_and_well_formed() {
+        let mut placements: Vec<String> = (0..Board::CHESS960_COUNT)
+            .map(|index| Board::chess960(index).unwrap().to_fen())
+            .collect();
+        for fen in &placements {
+            let back_rank = &fen[fen.rfind('/').unwrap() + 1..fen.find(' ').unwrap()];
+            let king = back_rank.find('K').unwrap();
+            let rooks: Vec<usize> = back_rank.match_indices('R').map(|(i, _)| i).collect();
+            let bishops: Vec<usize> = back_rank.match_indices('B').map(|(i, _)| i).collect();
+            assert!(rooks[0] < king && king < rooks[1], "{}", fen);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", fen);
+        }
+        placements.sort();
+        placements.dedup();
+        assert_eq!(placements.len(), Board::CHESS960_COUNT as usize);
+    }
+}
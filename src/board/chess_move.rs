@@ -1,32 +1,463 @@
-#[allow(dead_code)]
+use std::fmt::Display;
+use std::ops::Deref;
 
-use crate::board::{
-    square::SQ,
-    piece::PieceType
-};
+use crate::board::{Board, CastleSide, Color, File, Piece, PieceType, Rank, SQ};
 
+/// What kind of move a `Move` is. Bit 2 of the value marks captures and
+/// bit 3 promotions, whose low two bits then give the promoted piece.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Move {
-    from: SQ,
-    to: SQ,
-    promotion: Option<PieceType>
+#[repr(u8)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePush = 1,
+    KingCastle = 2,
+    QueenCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15
 }
 
+impl MoveFlag {
+    pub const COUNT: usize = 14;
+    pub const VARIANTS: [MoveFlag; MoveFlag::COUNT] = [
+        MoveFlag::Quiet,
+        MoveFlag::DoublePush,
+        MoveFlag::KingCastle,
+        MoveFlag::QueenCastle,
+        MoveFlag::Capture,
+        MoveFlag::EnPassant,
+        MoveFlag::KnightPromotion,
+        MoveFlag::BishopPromotion,
+        MoveFlag::RookPromotion,
+        MoveFlag::QueenPromotion,
+        MoveFlag::KnightPromotionCapture,
+        MoveFlag::BishopPromotionCapture,
+        MoveFlag::RookPromotionCapture,
+        MoveFlag::QueenPromotionCapture
+    ];
+
+    const CAPTURE_BIT: u8 = 4;
+    const PROMOTION_BIT: u8 = 8;
+
+    /// The flag with value `value`, if there is one; 6 and 7 are unused.
+    pub const fn from_u8(value: u8) -> Option<MoveFlag> {
+        match value {
+            0  => Some(MoveFlag::Quiet),
+            1  => Some(MoveFlag::DoublePush),
+            2  => Some(MoveFlag::KingCastle),
+            3  => Some(MoveFlag::QueenCastle),
+            4  => Some(MoveFlag::Capture),
+            5  => Some(MoveFlag::EnPassant),
+            8  => Some(MoveFlag::KnightPromotion),
+            9  => Some(MoveFlag::BishopPromotion),
+            10 => Some(MoveFlag::RookPromotion),
+            11 => Some(MoveFlag::QueenPromotion),
+            12 => Some(MoveFlag::KnightPromotionCapture),
+            13 => Some(MoveFlag::BishopPromotionCapture),
+            14 => Some(MoveFlag::RookPromotionCapture),
+            15 => Some(MoveFlag::QueenPromotionCapture),
+            _  => None
+        }
+    }
+
+    pub const fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// The flag for promoting to `promotion`, which must be a knight,
+    /// bishop, rook or queen.
+    pub fn promotion(promotion: PieceType, capture: bool) -> MoveFlag {
+        let piece_bits = match promotion {
+            PieceType::Knight => 0,
+            PieceType::Bishop => 1,
+            PieceType::Rook   => 2,
+            PieceType::Queen  => 3,
+            _ => panic!("cannot promote to {:?}", promotion)
+        };
+        let capture_bit = if capture { MoveFlag::CAPTURE_BIT } else { 0 };
+        MoveFlag::from_u8(MoveFlag::PROMOTION_BIT | capture_bit | piece_bits).expect("promotion flags are 8 to 15")
+    }
+}
+
+/// A move packed into 16 bits: the origin square in bits 0-5, the target
+/// square in bits 6-11 and its `MoveFlag` in bits 12-15. The flag records
+/// what the move does, so playing it needs no look at the board. A castle
+/// targets the castling rook's square, which tells the two castles apart
+/// in Chess960 even when the king does not move.
+///
+/// `to_parts` drops the flag, leaving the squares and promotion piece that
+/// UCI and opening books carry. The flag depends on the position, since
+/// `e5d6` may be a quiet move, a capture or an en passant capture, so only
+/// a board can put it back: `Board::move_from_parts` does so for every
+/// legal move.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(u16);
+
 impl Move {
-    pub fn new(from: SQ, to: SQ, promotion: Option<PieceType>) -> Self {
-        Move { from, to, promotion }
+    /// A placeholder for empty slots in move tables; never a legal move.
+    pub const NULL: Move = Move::new(SQ::A1, SQ::A1, MoveFlag::Quiet);
+
+    pub const fn new(from: SQ, to: SQ, flag: MoveFlag) -> Self {
+        Move(from.to_u8() as u16 | (to.to_u8() as u16) << 6 | (flag.to_u8() as u16) << 12)
+    }
+
+    /// The move encoded as `bits`, unless its flag is one of the unused values.
+    pub const fn from_u16(bits: u16) -> Option<Move> {
+        match MoveFlag::from_u8((bits >> 12) as u8) {
+            Some(_) => Some(Move(bits)),
+            None => None
+        }
+    }
+
+    pub const fn to_u16(self) -> u16 {
+        self.0
     }
 
     pub fn origin_sq(self) -> SQ {
-        self.from
+        SQ::from_u8((self.0 & 0x3f) as u8)
     }
 
     pub fn target_sq(self) -> SQ {
-        self.to
+        SQ::from_u8((self.0 >> 6 & 0x3f) as u8)
+    }
+
+    /// The origin square, target square and promotion piece, the parts of
+    /// the move that do not depend on the position.
+    pub fn to_parts(self) -> (SQ, SQ, Option<PieceType>) {
+        (self.origin_sq(), self.target_sq(), self.promotion())
+    }
+
+    pub fn flag(self) -> MoveFlag {
+        MoveFlag::from_u8((self.0 >> 12) as u8).expect("a move is only built with a valid flag")
     }
 
     pub fn promotion(self) -> Option<PieceType> {
-        self.promotion
+        self.is_promotion().then(|| PieceType::VARIANTS[PieceType::Knight as usize + (self.0 >> 12 & 3) as usize])
+    }
+
+    /// Whether the move takes a piece, en passant included.
+    pub fn is_capture(self) -> bool {
+        (self.0 >> 12) as u8 & MoveFlag::CAPTURE_BIT != 0
+    }
+
+    pub fn is_promotion(self) -> bool {
+        (self.0 >> 12) as u8 & MoveFlag::PROMOTION_BIT != 0
+    }
+
+    pub fn is_castle(self) -> bool {
+        matches!(self.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.flag() == MoveFlag::EnPassant
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.flag() == MoveFlag::DoublePush
+    }
+
+    /// Where the king and rook of a castle end up: the g- and f-files
+    /// kingside and the c- and d-files queenside, wherever they started.
+    pub fn castle_destinations(self) -> (SQ, SQ) {
+        debug_assert!(self.is_castle());
+        let rank = self.origin_sq().rank();
+        let (king_file, rook_file) = match self.flag() {
+            MoveFlag::KingCastle => (File::G, File::F),
+            _                    => (File::C, File::D)
+        };
+        (SQ::from_coords(king_file, rank), SQ::from_coords(rook_file, rank))
+    }
+
+    /// The move in UCI notation. Castles name the king's destination, e.g.
+    /// `e1g1`, unless `chess960` is set, when they name the castling rook's
+    /// square instead, e.g. `e1h1`.
+    pub fn to_uci(self, chess960: bool) -> String {
+        if chess960 && self.is_castle() {
+            format!("{}{}", self.origin_sq(), self.target_sq())
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Move")
+            .field("from", &self.origin_sq())
+            .field("to", &self.target_sq())
+            .field("flag", &self.flag())
+            .finish()
+    }
+}
+
+/// Formats the move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`,
+/// with castles written as the king's move.
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to = if self.is_castle() { self.castle_destinations().0 } else { self.target_sq() };
+        write!(f, "{}{}", self.origin_sq(), to)?;
+        if let Some(promotion) = self.promotion() {
+            write!(f, "{}", Piece::new(Color::Black, promotion).to_char())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveParseError {
+    InvalidLength(usize),
+    InvalidSquare,
+    InvalidPromotion(char),
+    IllegalMove(Move)
+}
+
+impl Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveParseError::InvalidLength(len) => write!(f, "expected 4 or 5 characters, found {}", len),
+            MoveParseError::InvalidSquare => write!(f, "invalid square"),
+            MoveParseError::InvalidPromotion(c) => write!(f, "invalid promotion piece '{}'", c),
+            MoveParseError::IllegalMove(m) => write!(f, "illegal move {}", m)
+        }
+    }
+}
+
+impl std::error::Error for MoveParseError {}
+
+impl Board {
+    /// Parses a move in UCI long algebraic notation and checks that it is
+    /// legal in this position. Castles must be written as `Move::to_uci`
+    /// writes them for `chess960`: as the king's destination, e.g. `e1g1`,
+    /// or as the king taking its rook, e.g. `e1h1`. Written the other way,
+    /// the squares are read as a plain king move.
+    pub fn parse_uci_move(&self, s: &str, chess960: bool) -> Result<Move, MoveParseError> {
+        let len = s.chars().count();
+        if !(4..=5).contains(&len) || !s.is_ascii() {
+            return Err(MoveParseError::InvalidLength(len));
+        }
+
+        let from: SQ = s[0..2].parse().map_err(|_| MoveParseError::InvalidSquare)?;
+        let to: SQ = s[2..4].parse().map_err(|_| MoveParseError::InvalidSquare)?;
+        let promotion = match s[4..].chars().next() {
+            None      => None,
+            Some('n') => Some(PieceType::Knight),
+            Some('b') => Some(PieceType::Bishop),
+            Some('r') => Some(PieceType::Rook),
+            Some('q') => Some(PieceType::Queen),
+            Some(c)   => return Err(MoveParseError::InvalidPromotion(c))
+        };
+
+        let mut m = self.infer_move(from, to, promotion);
+        if m.is_castle() && m.to_uci(chess960) != s {
+            let flag = if self.pieces[to].is_some() { MoveFlag::Capture } else { MoveFlag::Quiet };
+            m = Move::new(from, to, flag);
+        }
+        if self.legal_moves().contains(&m) {
+            Ok(m)
+        } else {
+            Err(MoveParseError::IllegalMove(m))
+        }
+    }
+
+    /// The move from `from` to `to`, promoting to `promotion` if given,
+    /// with its flag worked out from this position. This turns a move
+    /// known only by its squares, as UCI and opening books give them, back
+    /// into the move generation would produce. A castle may be given as the
+    /// king taking its own rook or, from the e-file, as the king's two-step
+    /// move. The move need not be legal, but `promotion` must be a piece a
+    /// pawn can become.
+    pub fn infer_move(&self, from: SQ, to: SQ, promotion: Option<PieceType>) -> Move {
+        if let Some(side) = self.castle_side(from, to) {
+            let color = self.side_to_move;
+            let flag = match side {
+                CastleSide::King  => MoveFlag::KingCastle,
+                CastleSide::Queen => MoveFlag::QueenCastle
+            };
+            return Move::new(from, self.castle_rook_sq(color, side), flag);
+        }
+
+        let capture = self.pieces[to].is_some();
+        let piece_type = self.pieces[from].map(Piece::type_of);
+        let flag = if let Some(promotion) = promotion {
+            MoveFlag::promotion(promotion, capture)
+        } else if piece_type == Some(PieceType::Pawn) && Some(to) == self.en_passant && from.file() != to.file() {
+            MoveFlag::EnPassant
+        } else if piece_type == Some(PieceType::Pawn) && from.rank().to_u8().abs_diff(to.rank().to_u8()) == 2 {
+            MoveFlag::DoublePush
+        } else if capture {
+            MoveFlag::Capture
+        } else {
+            MoveFlag::Quiet
+        };
+        Move::new(from, to, flag)
+    }
+
+    /// The legal move whose `Move::to_parts` are `from`, `to` and
+    /// `promotion`, if there is one. Castles are the king taking its own
+    /// rook, as `Move` stores them.
+    pub fn move_from_parts(&self, from: SQ, to: SQ, promotion: Option<PieceType>) -> Option<Move> {
+        self.legal_moves().into_iter().find(|m| m.to_parts() == (from, to, promotion))
+    }
+
+    /// The side the king of the side to move castles to when it moves from
+    /// `from` to `to`, if that is how `infer_move` reads the squares.
+    fn castle_side(&self, from: SQ, to: SQ) -> Option<CastleSide> {
+        let us = self.side_to_move;
+        let rank = Rank::back_rank(us);
+        if self.pieces[from] != Some(Piece::new(us, PieceType::King)) || from.rank() != rank || to.rank() != rank {
+            return None;
+        }
+
+        let side = if to.file() > from.file() { CastleSide::King } else { CastleSide::Queen };
+        let takes_rook = to == self.castle_rook_sq(us, side) && self.pieces[to] == Some(Piece::new(us, PieceType::Rook));
+        let two_step = from.file() == File::E && from.file().to_u8().abs_diff(to.file().to_u8()) == 2;
+        (takes_rook || two_step).then_some(side)
+    }
+}
+
+/// A fixed-capacity list of moves that lives on the stack, with a score
+/// kept alongside each move for ordering.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MoveList::CAPACITY],
+    scores: [i32; MoveList::CAPACITY],
+    len: usize
+}
+
+impl MoveList {
+    /// No legal chess position has more than 218 moves.
+    pub const CAPACITY: usize = 256;
+
+    pub const fn new() -> Self {
+        MoveList {
+            moves: [Move::NULL; MoveList::CAPACITY],
+            scores: [0; MoveList::CAPACITY],
+            len: 0
+        }
+    }
+
+    pub fn push(&mut self, m: Move) {
+        self.push_scored(m, 0);
+    }
+
+    pub fn push_scored(&mut self, m: Move, score: i32) {
+        debug_assert!(self.len < MoveList::CAPACITY);
+        self.moves[self.len] = m;
+        self.scores[self.len] = score;
+        self.len += 1;
+    }
+
+    /// Removes the move at `index` by moving the last move into its place.
+    pub fn swap_remove(&mut self, index: usize) -> Move {
+        assert!(index < self.len, "swap_remove index {} out of bounds for length {}", index, self.len);
+        let m = self.moves[index];
+        self.len -= 1;
+        self.moves[index] = self.moves[self.len];
+        self.scores[index] = self.scores[self.len];
+        m
+    }
+
+    /// Swaps two moves together with their scores.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.moves[..self.len].swap(a, b);
+        self.scores[..self.len].swap(a, b);
+    }
+
+    pub fn scores(&self) -> &[i32] {
+        &self.scores[..self.len]
+    }
+
+    pub fn scores_mut(&mut self) -> &mut [i32] {
+        &mut self.scores[..self.len]
+    }
+
+    /// Moves the highest-scored move at or after `start` to `start` and
+    /// returns it, so a search can order lazily one move at a time.
+    pub fn pick_best(&mut self, start: usize) -> Option<(Move, i32)> {
+        if start >= self.len {
+            return None;
+        }
+
+        let mut best = start;
+        for i in start + 1..self.len {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+
+        self.swap(start, best);
+        Some((self.moves[start], self.scores[start]))
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MoveListIntoIter { list: self, index: 0 }
+    }
+}
+
+pub struct MoveListIntoIter {
+    list: MoveList,
+    index: usize
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.list.get(self.index).copied();
+        self.index += 1;
+        m
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 
@@ -36,7 +467,7 @@ mod tests {
 
     #[test]
     fn create_move_with_no_promotion_and_access_its_fields() {
-        let chess_move = Move::new(SQ::E2, SQ::E4, None);
+        let chess_move = Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet);
         assert_eq!(chess_move.origin_sq(), SQ::E2);
         assert_eq!(chess_move.target_sq(), SQ::E4);
         assert_eq!(chess_move.promotion(), None);
@@ -44,14 +475,175 @@ mod tests {
 
     #[test]
     fn create_move_with_promotion_and_access_its_fields() {
-        let chess_move = Move::new(SQ::A1, SQ::B2, Some(PieceType::Queen));
+        let chess_move = Move::new(SQ::A1, SQ::B2, MoveFlag::QueenPromotion);
         assert_eq!(chess_move.origin_sq(), SQ::A1);
         assert_eq!(chess_move.target_sq(), SQ::B2);
         assert_eq!(chess_move.promotion(), Some(PieceType::Queen));
 
-        let chess_move = Move::new(SQ::H7, SQ::H8, Some(PieceType::Knight));
+        let chess_move = Move::new(SQ::H7, SQ::H8, MoveFlag::KnightPromotion);
         assert_eq!(chess_move.origin_sq(), SQ::H7);
         assert_eq!(chess_move.target_sq(), SQ::H8);
         assert_eq!(chess_move.promotion(), Some(PieceType::Knight));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn flags_round_trip_through_16_bits() {
+        for flag in MoveFlag::VARIANTS {
+            assert_eq!(MoveFlag::from_u8(flag.to_u8()), Some(flag));
+            let m = Move::new(SQ::H7, SQ::G8, flag);
+            assert_eq!(Move::from_u16(m.to_u16()), Some(m));
+            assert_eq!((m.origin_sq(), m.target_sq(), m.flag()), (SQ::H7, SQ::G8, flag));
+        }
+        assert_eq!(MoveFlag::from_u8(6), None);
+        assert_eq!(Move::from_u16(7 << 12), None);
+        assert_eq!(std::mem::size_of::<Move>(), 2);
+    }
+
+    #[test]
+    fn flags_classify_moves() {
+        let en_passant = Move::new(SQ::E5, SQ::D6, MoveFlag::EnPassant);
+        assert!(en_passant.is_capture() && en_passant.is_en_passant() && !en_passant.is_promotion());
+
+        let promotion = Move::new(SQ::B7, SQ::A8, MoveFlag::promotion(PieceType::Rook, true));
+        assert_eq!(promotion.flag(), MoveFlag::RookPromotionCapture);
+        assert!(promotion.is_capture() && promotion.is_promotion());
+        assert_eq!(promotion.promotion(), Some(PieceType::Rook));
+
+        let castle = Move::new(SQ::E1, SQ::A1, MoveFlag::QueenCastle);
+        assert!(castle.is_castle() && !castle.is_capture() && castle.promotion().is_none());
+        assert!(Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush).is_double_push());
+    }
+
+    #[test]
+    fn display_uses_uci_notation() {
+        assert_eq!(Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet).to_string(), "e2e4");
+        assert_eq!(Move::new(SQ::E7, SQ::E8, MoveFlag::QueenPromotion).to_string(), "e7e8q");
+        assert_eq!(Move::new(SQ::A2, SQ::B1, MoveFlag::KnightPromotion).to_string(), "a2b1n");
+    }
+
+    #[test]
+    fn castles_in_standard_and_chess960_notation() {
+        let castle = Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle);
+        assert_eq!(castle.castle_destinations(), (SQ::G1, SQ::F1));
+        assert_eq!(castle.to_string(), "e1g1");
+        assert_eq!(castle.to_uci(false), "e1g1");
+        assert_eq!(castle.to_uci(true), "e1h1");
+
+        let castle = Move::new(SQ::B8, SQ::A8, MoveFlag::QueenCastle);
+        assert_eq!(castle.castle_destinations(), (SQ::C8, SQ::D8));
+        assert_eq!(castle.to_uci(false), "b8c8");
+        assert_eq!(castle.to_uci(true), "b8a8");
+        assert_eq!(Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush).to_uci(true), "e2e4");
+    }
+
+    #[test]
+    fn parse_chess960_castles() {
+        // The king castles kingside without moving, so only the rook's square names it.
+        let board = Board::from_fen("1r4kr/8/8/8/8/8/8/RR4KR w HBhb - 0 1").unwrap();
+        assert_eq!(board.parse_uci_move("g1h1", true), Ok(Move::new(SQ::G1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.parse_uci_move("g1b1", true), Ok(Move::new(SQ::G1, SQ::B1, MoveFlag::QueenCastle)));
+        assert_eq!(board.parse_uci_move("g1a1", true), Err(MoveParseError::IllegalMove(Move::new(SQ::G1, SQ::A1, MoveFlag::Capture))));
+        assert_eq!(board.parse_uci_move("g1c1", true), Err(MoveParseError::IllegalMove(Move::new(SQ::G1, SQ::C1, MoveFlag::Quiet))));
+    }
+
+    #[test]
+    fn parse_uci_move() {
+        let board = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(board.parse_uci_move("e1g1", false), Ok(Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.parse_uci_move("e1h1", true), Ok(Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.parse_uci_move("b7a8r", false), Ok(Move::new(SQ::B7, SQ::A8, MoveFlag::RookPromotionCapture)));
+        assert_eq!(board.parse_uci_move("b7b8q", false), Ok(Move::new(SQ::B7, SQ::B8, MoveFlag::QueenPromotion)));
+
+        assert_eq!(board.parse_uci_move("e1", false), Err(MoveParseError::InvalidLength(2)));
+        assert_eq!(board.parse_uci_move("b7b8qq", false), Err(MoveParseError::InvalidLength(6)));
+        assert_eq!(board.parse_uci_move("i1g1", false), Err(MoveParseError::InvalidSquare));
+        assert_eq!(board.parse_uci_move("b7b8k", false), Err(MoveParseError::InvalidPromotion('k')));
+        assert_eq!(board.parse_uci_move("b7b8Q", false), Err(MoveParseError::InvalidPromotion('Q')));
+        assert_eq!(board.parse_uci_move("b7b8", false), Err(MoveParseError::IllegalMove(Move::new(SQ::B7, SQ::B8, MoveFlag::Quiet))));
+        assert_eq!(board.parse_uci_move("e1e3", false), Err(MoveParseError::IllegalMove(Move::new(SQ::E1, SQ::E3, MoveFlag::Quiet))));
+
+        // Each castle notation only counts when it is the one asked for.
+        assert_eq!(board.parse_uci_move("e1h1", false), Err(MoveParseError::IllegalMove(Move::new(SQ::E1, SQ::H1, MoveFlag::Capture))));
+        assert_eq!(board.parse_uci_move("e1g1", true), Err(MoveParseError::IllegalMove(Move::new(SQ::E1, SQ::G1, MoveFlag::Quiet))));
+    }
+
+    #[test]
+    fn move_from_parts() {
+        let board = Board::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        assert_eq!(board.move_from_parts(SQ::E5, SQ::D6, None), Some(Move::new(SQ::E5, SQ::D6, MoveFlag::EnPassant)));
+        assert_eq!(board.move_from_parts(SQ::E1, SQ::H1, None), Some(Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.move_from_parts(SQ::B7, SQ::A8, Some(PieceType::Knight)), Some(Move::new(SQ::B7, SQ::A8, MoveFlag::KnightPromotionCapture)));
+        assert_eq!(board.move_from_parts(SQ::E1, SQ::G1, None), None);
+        assert_eq!(board.move_from_parts(SQ::B7, SQ::B8, None), None);
+        assert_eq!(board.move_from_parts(SQ::A1, SQ::A1, None), None);
+        assert!(!board.legal_moves().contains(&Move::NULL));
+    }
+
+    #[test]
+    fn every_legal_move_round_trips_through_uci() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for m in board.legal_moves() {
+            assert_eq!(board.parse_uci_move(&m.to_uci(false), false), Ok(m));
+            assert_eq!(board.parse_uci_move(&m.to_uci(true), true), Ok(m));
+        }
+    }
+
+    fn sample_list() -> MoveList {
+        let mut list = MoveList::new();
+        list.push_scored(Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet), 10);
+        list.push_scored(Move::new(SQ::D2, SQ::D4, MoveFlag::Quiet), 30);
+        list.push_scored(Move::new(SQ::G1, SQ::F3, MoveFlag::Quiet), 20);
+        list
+    }
+
+    #[test]
+    fn push_and_deref() {
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+
+        list.push(Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet));
+        list.push(Move::new(SQ::B1, SQ::C3, MoveFlag::Quiet));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1], Move::new(SQ::B1, SQ::C3, MoveFlag::Quiet));
+        assert_eq!(list.scores(), &[0, 0]);
+        assert!(list.contains(&Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet)));
+
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn swap_remove_keeps_scores_in_step() {
+        let mut list = sample_list();
+        assert_eq!(list.swap_remove(0), Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet));
+        assert_eq!(&list[..], &[Move::new(SQ::G1, SQ::F3, MoveFlag::Quiet), Move::new(SQ::D2, SQ::D4, MoveFlag::Quiet)]);
+        assert_eq!(list.scores(), &[20, 30]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_remove_out_of_bounds() {
+        let mut list = sample_list();
+        list.swap_remove(3);
+    }
+
+    #[test]
+    fn pick_best_orders_by_score() {
+        let mut list = sample_list();
+        list.scores_mut()[0] = 25;
+
+        assert_eq!(list.pick_best(0), Some((Move::new(SQ::D2, SQ::D4, MoveFlag::Quiet), 30)));
+        assert_eq!(list.pick_best(1), Some((Move::new(SQ::E2, SQ::E4, MoveFlag::Quiet), 25)));
+        assert_eq!(list.pick_best(2), Some((Move::new(SQ::G1, SQ::F3, MoveFlag::Quiet), 20)));
+        assert_eq!(list.pick_best(3), None);
+    }
+
+    #[test]
+    fn into_iterator() {
+        let list = sample_list();
+        let borrowed: Vec<Move> = (&list).into_iter().copied().collect();
+        let owned: Vec<Move> = list.clone().into_iter().collect();
+        assert_eq!(borrowed, owned);
+        assert_eq!(list.into_iter().len(), 3);
+    }
+}
@@ -1,5 +1,3 @@
-#[allow(dead_code)]
-
 use std::ops::{Not, Index, IndexMut};
 use std::str::FromStr;
 
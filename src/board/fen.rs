@@ -0,0 +1,531 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::board::{BB, Board, CastleRights, CastleSide, Color, File, Piece, PieceType, Rank, SQ};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenField {
+    PiecePlacement,
+    SideToMove,
+    CastleRights,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber
+}
+
+impl Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FenField::PiecePlacement => "piece placement",
+            FenField::SideToMove     => "side to move",
+            FenField::CastleRights   => "castle rights",
+            FenField::EnPassant      => "en passant",
+            FenField::HalfmoveClock  => "halfmove clock",
+            FenField::FullmoveNumber => "fullmove number"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    InvalidTokenCount(usize),
+    InvalidRankCount(usize),
+    InvalidPiece { rank: Rank, c: char },
+    InvalidSquareCount { rank: Rank },
+    InvalidKingCount(Color),
+    InvalidSideToMove(char),
+    OpponentInCheck,
+    InvalidCastleRights(char),
+    InvalidEnPassant(char),
+    InvalidHalfmoveClock(char),
+    InvalidFullmoveNumber(char)
+}
+
+impl FenError {
+    pub fn field(self) -> Option<FenField> {
+        match self {
+            FenError::InvalidTokenCount(_)     => None,
+            FenError::InvalidRankCount(_)      |
+            FenError::InvalidPiece { .. }      |
+            FenError::InvalidSquareCount { .. } |
+            FenError::InvalidKingCount(_)      => Some(FenField::PiecePlacement),
+            FenError::InvalidSideToMove(_)     |
+            FenError::OpponentInCheck          => Some(FenField::SideToMove),
+            FenError::InvalidCastleRights(_)   => Some(FenField::CastleRights),
+            FenError::InvalidEnPassant(_)      => Some(FenField::EnPassant),
+            FenError::InvalidHalfmoveClock(_)  => Some(FenField::HalfmoveClock),
+            FenError::InvalidFullmoveNumber(_) => Some(FenField::FullmoveNumber)
+        }
+    }
+
+    pub fn rank(self) -> Option<Rank> {
+        match self {
+            FenError::InvalidPiece { rank, .. } | FenError::InvalidSquareCount { rank } => Some(rank),
+            _ => None
+        }
+    }
+
+    pub fn character(self) -> Option<char> {
+        match self {
+            FenError::InvalidPiece { c, .. }   |
+            FenError::InvalidSideToMove(c)     |
+            FenError::InvalidCastleRights(c)   |
+            FenError::InvalidEnPassant(c)      |
+            FenError::InvalidHalfmoveClock(c)  |
+            FenError::InvalidFullmoveNumber(c) => Some(c),
+            _ => None
+        }
+    }
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::InvalidTokenCount(count) => {
+                write!(f, "expected 4 to 6 fields, found {}", count)
+            },
+            FenError::InvalidRankCount(count) => {
+                write!(f, "{}: expected {} ranks, found {}", FenField::PiecePlacement, Rank::COUNT, count)
+            },
+            FenError::InvalidPiece { rank, c } => {
+                write!(f, "{}: invalid character '{}' on rank {}", FenField::PiecePlacement, c, rank)
+            },
+            FenError::InvalidSquareCount { rank } => {
+                write!(f, "{}: rank {} does not describe {} squares", FenField::PiecePlacement, rank, File::COUNT)
+            },
+            FenError::InvalidKingCount(color) => {
+                write!(f, "{}: {:?} must have exactly one king", FenField::PiecePlacement, color)
+            },
+            FenError::OpponentInCheck => {
+                write!(f, "{}: the side not to move is in check", FenField::SideToMove)
+            },
+            _ => {
+                let field = self.field().expect("every remaining error belongs to a field");
+                let c = self.character().expect("every remaining error carries a character");
+                write!(f, "{}: invalid character '{}'", field, c)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Returns the first character of `token`, used to point at the offending
+/// input when a whole token is malformed.
+fn first_char(token: &str) -> char {
+    token.chars().next().unwrap_or(' ')
+}
+
+impl Board {
+    /// Parses a position in Forsyth-Edwards Notation. The halfmove clock and
+    /// fullmove number may be omitted, in which case they default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let tokens: Vec<&str> = fen.split_whitespace().collect();
+        if tokens.len() < 4 || tokens.len() > 6 {
+            return Err(FenError::InvalidTokenCount(tokens.len()));
+        }
+
+        let mut board = Board::default();
+        Board::parse_fen_position(tokens[0], &mut board)?;
+
+        board.side_to_move = match tokens[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            token => return Err(FenError::InvalidSideToMove(first_char(token)))
+        };
+
+        // The side to move could capture the king, so the position cannot
+        // have arisen and the move generator cannot handle it.
+        let them = !board.side_to_move;
+        let attackers = board.attackers_to(board.king_sq(them), board.occupied_bb());
+        if attackers & board.color_bb(board.side_to_move) != BB::EMPTY {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        board.parse_fen_castle_rights(tokens[2])?;
+        board.en_passant = board.parse_fen_en_passant(tokens[3])?;
+
+        if let Some(token) = tokens.get(4) {
+            board.halfmove_clock = parse_fen_number(token)
+                .map_err(FenError::InvalidHalfmoveClock)?;
+        }
+
+        if let Some(token) = tokens.get(5) {
+            board.fullmove_number = parse_fen_number(token)
+                .map_err(FenError::InvalidFullmoveNumber)?;
+        }
+
+        board.hash = board.compute_hash_from_scratch();
+        Ok(board)
+    }
+
+    /// Formats the position in FEN. Castle rights use `KQkq`, falling back
+    /// to the rook's file as X-FEN does when an outer rook on the same side
+    /// would make the letter ambiguous.
+    pub fn to_fen(&self) -> String {
+        self.format_fen(false)
+    }
+
+    /// Formats the position in Shredder-FEN, which names every castle
+    /// right by its rook's file, e.g. `HAha` for the standard rights.
+    pub fn to_shredder_fen(&self) -> String {
+        self.format_fen(true)
+    }
+
+    fn format_fen(&self, shredder: bool) -> String {
+        let mut fen = String::new();
+
+        for rank in Rank::VARIANTS.iter().rev().copied() {
+            let mut empty = 0;
+            for file in File::VARIANTS.iter().copied() {
+                match self.piece_on(SQ::from_coords(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
+                    },
+                    None => empty += 1
+                }
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+
+            if rank != Rank::First {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.side_to_move {
+            Color::White => 'w',
+            Color::Black => 'b'
+        });
+
+        fen.push(' ');
+        if self.castle_rights.is_empty() {
+            fen.push('-');
+        }
+        for color in Color::VARIANTS {
+            for side in CastleSide::VARIANTS {
+                if !self.castle_rights.has(color, side) {
+                    continue;
+                }
+                let file = self.castle_rook_files[color][side];
+                let c = if shredder || self.outermost_rook(color, side).is_some_and(|outer| outer != file) {
+                    file.to_char()
+                } else {
+                    match side {
+                        CastleSide::King  => 'k',
+                        CastleSide::Queen => 'q'
+                    }
+                };
+                fen.push(if color == Color::White { c.to_ascii_uppercase() } else { c });
+            }
+        }
+
+        fen.push(' ');
+        match self.en_passant {
+            Some(sq) => fen.push_str(&sq.to_string()),
+            None => fen.push('-')
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+        fen
+    }
+
+    fn parse_fen_position(position: &str, board: &mut Board) -> Result<(), FenError> {
+        let rows: Vec<&str> = position.split('/').collect();
+        if rows.len() != Rank::COUNT {
+            return Err(FenError::InvalidRankCount(rows.len()));
+        }
+
+        for (row, rank) in rows.iter().zip(Rank::VARIANTS.iter().rev().copied()) {
+            let mut file_index = 0;
+            for c in row.chars() {
+                if file_index >= File::COUNT {
+                    return Err(FenError::InvalidSquareCount { rank });
+                }
+
+                if let Some(digit) = c.to_digit(10) {
+                    if digit == 0 || digit as usize > File::COUNT {
+                        return Err(FenError::InvalidPiece { rank, c });
+                    }
+                    file_index += digit as usize;
+                } else {
+                    let Some(piece) = Piece::try_from_char(c) else {
+                        return Err(FenError::InvalidPiece { rank, c });
+                    };
+
+                    board.add_piece(SQ::from_coords(File::from_u8(file_index as u8), rank), piece);
+                    file_index += 1;
+                }
+            }
+
+            if file_index != File::COUNT {
+                return Err(FenError::InvalidSquareCount { rank });
+            }
+        }
+
+        for color in Color::VARIANTS {
+            if board.piece_count(Piece::new(color, PieceType::King)) != 1 {
+                return Err(FenError::InvalidKingCount(color));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the castle rights field onto a board whose pieces are placed.
+    /// `K` and `Q` castle with the outermost rook on that side of the king,
+    /// while a file letter, as in Shredder-FEN and X-FEN, names the rook's
+    /// file directly. Every right needs the king on its back rank and the
+    /// rook it castles with.
+    fn parse_fen_castle_rights(&mut self, token: &str) -> Result<(), FenError> {
+        if token != "-" {
+            for c in token.chars() {
+                let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+                let king_sq = self.king_sq(color);
+                if king_sq.rank() != Rank::back_rank(color) {
+                    return Err(FenError::InvalidCastleRights(c));
+                }
+
+                let king_file = king_sq.file();
+                let rook = Piece::new(color, PieceType::Rook);
+                let (side, rook_file) = match c.to_ascii_lowercase() {
+                    'k' => (CastleSide::King, self.outermost_rook(color, CastleSide::King)),
+                    'q' => (CastleSide::Queen, self.outermost_rook(color, CastleSide::Queen)),
+                    lower => match File::from_char(lower) {
+                        Some(file) if self.piece_on(SQ::from_coords(file, king_sq.rank())) != Some(rook) => {
+                            return Err(FenError::InvalidCastleRights(c));
+                        },
+                        Some(file) if file > king_file => (CastleSide::King, Some(file)),
+                        Some(file) if file < king_file => (CastleSide::Queen, Some(file)),
+                        _ => return Err(FenError::InvalidCastleRights(c))
+                    }
+                };
+                let Some(rook_file) = rook_file else {
+                    return Err(FenError::InvalidCastleRights(c));
+                };
+
+                let right = CastleRights::new(color, side);
+                if self.castle_rights.contains(right) {
+                    return Err(FenError::InvalidCastleRights(c));
+                }
+                self.castle_rights.insert(right);
+                self.castle_rook_files[color][side] = rook_file;
+            }
+        }
+
+        self.update_castle_masks();
+        Ok(())
+    }
+
+    /// The file of `color`'s rook on its back rank furthest from the king
+    /// towards `side`, if there is one.
+    fn outermost_rook(&self, color: Color, side: CastleSide) -> Option<File> {
+        let king_file = self.king_sq(color).file();
+        let rooks = self.piece_bb(Piece::new(color, PieceType::Rook)) & BB::from_rank(Rank::back_rank(color));
+        let files = rooks.into_iter().map(SQ::file);
+        match side {
+            CastleSide::King  => files.filter(|&file| file > king_file).max(),
+            CastleSide::Queen => files.filter(|&file| file < king_file).min()
+        }
+    }
+
+    /// Parses the en passant field onto a board whose pieces and side to
+    /// move are set. The square must be one an enemy pawn just skipped over
+    /// with a double push: empty, with its start square empty and the pawn
+    /// in front of it.
+    fn parse_fen_en_passant(&self, token: &str) -> Result<Option<SQ>, FenError> {
+        if token == "-" {
+            return Ok(None);
+        }
+
+        let sq = SQ::from_str(token).map_err(|_| FenError::InvalidEnPassant(first_char(token)))?;
+        let them = !self.side_to_move;
+        let expected_rank = match self.side_to_move {
+            Color::White => Rank::Sixth,
+            Color::Black => Rank::Third
+        };
+
+        if sq.rank() != expected_rank {
+            return Err(FenError::InvalidEnPassant(sq.rank().to_char()));
+        }
+
+        let from = sq.backward(them).expect("the en passant rank is not a back rank");
+        let pawn = sq.forward(them).expect("the en passant rank is not a back rank");
+        if self.piece_on(sq).is_some()
+            || self.piece_on(from).is_some()
+            || self.piece_on(pawn) != Some(Piece::new(them, PieceType::Pawn)) {
+            return Err(FenError::InvalidEnPassant(sq.file().to_char()));
+        }
+
+        Ok(Some(sq))
+    }
+}
+
+fn parse_fen_number<T: FromStr>(token: &str) -> Result<T, char> {
+    if let Some(c) = token.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(c);
+    }
+    token.parse().map_err(|_| first_char(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_reports_field_rank_and_character() {
+        let err = Board::from_fen("rnbqkbnr/pppppppp/8/8/4X3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidPiece { rank: Rank::Fourth, c: 'X' });
+        assert_eq!(err.field(), Some(FenField::PiecePlacement));
+        assert_eq!(err.rank(), Some(Rank::Fourth));
+        assert_eq!(err.character(), Some('X'));
+    }
+
+    #[test]
+    fn invalid_token_count() {
+        assert_eq!(Board::from_fen(""), Err(FenError::InvalidTokenCount(0)));
+        assert_eq!(Board::from_fen("8/8/8/8/8/8/8/8 w -"), Err(FenError::InvalidTokenCount(3)));
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra"),
+            Err(FenError::InvalidTokenCount(7))
+        );
+    }
+
+    #[test]
+    fn invalid_piece_placement() {
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(FenError::InvalidRankCount(7))
+        );
+        assert_eq!(
+            Board::from_fen("4k3/9/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(FenError::InvalidPiece { rank: Rank::Seventh, c: '9' })
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K4 w - - 0 1"),
+            Err(FenError::InvalidSquareCount { rank: Rank::First })
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/7/8/8/8/4K3 w - - 0 1"),
+            Err(FenError::InvalidSquareCount { rank: Rank::Fifth })
+        );
+        assert_eq!(
+            Board::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(FenError::InvalidKingCount(Color::Black))
+        );
+    }
+
+    #[test]
+    fn invalid_state_fields() {
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidSideToMove('x'));
+        assert_eq!(err.field(), Some(FenField::SideToMove));
+
+        let err = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkx - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('x'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KK - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('K'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - e3 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidEnPassant('3'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - z6 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidEnPassant('z'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 1a 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidHalfmoveClock('a'));
+        assert_eq!(err.field(), Some(FenField::HalfmoveClock));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 -1").unwrap_err();
+        assert_eq!(err, FenError::InvalidFullmoveNumber('-'));
+        assert_eq!(err.field(), Some(FenField::FullmoveNumber));
+    }
+
+    #[test]
+    fn castle_rights_in_shredder_and_x_fen() {
+        let standard = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let shredder = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+        assert_eq!(shredder, standard);
+        assert_eq!(standard.to_shredder_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1");
+
+        // X-FEN names an inner rook by its file, since Q would mean a1.
+        let board = Board::from_fen("1r2k2r/8/8/8/8/8/8/R1R1K2R w KCkq - 0 1").unwrap();
+        assert_eq!(board.castle_rook_sq(Color::White, CastleSide::Queen), SQ::C1);
+        assert_eq!(board.castle_rook_sq(Color::Black, CastleSide::Queen), SQ::B8);
+        assert_eq!(board.to_fen(), "1r2k2r/8/8/8/8/8/8/R1R1K2R w KCkq - 0 1");
+        assert_eq!(board.to_shredder_fen(), "1r2k2r/8/8/8/8/8/8/R1R1K2R w HChb - 0 1");
+        assert_eq!(Board::from_fen(&board.to_shredder_fen()).unwrap(), board);
+    }
+
+    #[test]
+    fn invalid_castle_rights() {
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w E - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('E'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KH - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('H'));
+
+        // A right needs the rook it castles with.
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('K'));
+
+        let err = Board::from_fen("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('k'));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w G - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('G'));
+
+        // ...and the king on its back rank.
+        let err = Board::from_fen("4k3/8/8/8/8/8/4K3/R6R w KQ - 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidCastleRights('K'));
+    }
+
+    #[test]
+    fn invalid_en_passant() {
+        // No pawn in front of the square.
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - e6 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidEnPassant('e'));
+
+        // The pawn cannot have come from e7, and e6 itself is occupied.
+        let err = Board::from_fen("4k3/4p3/8/3Pp3/8/8/8/4K3 w - e6 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidEnPassant('e'));
+
+        let err = Board::from_fen("4k3/8/4p3/3P4/8/8/8/4K3 w - e6 0 1").unwrap_err();
+        assert_eq!(err, FenError::InvalidEnPassant('e'));
+
+        let board = Board::from_fen("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1").unwrap();
+        assert_eq!(board.en_passant(), Some(SQ::E6));
+    }
+
+    #[test]
+    fn opponent_in_check() {
+        let err = Board::from_fen("4k3/4K3/8/8/8/8/8/8 w - - 0 1").unwrap_err();
+        assert_eq!(err, FenError::OpponentInCheck);
+        assert_eq!(err.field(), Some(FenField::SideToMove));
+
+        let err = Board::from_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").unwrap_err();
+        assert_eq!(err, FenError::OpponentInCheck);
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            FenError::InvalidPiece { rank: Rank::Fourth, c: 'X' }.to_string(),
+            "piece placement: invalid character 'X' on rank 4"
+        );
+        assert_eq!(
+            FenError::InvalidCastleRights('x').to_string(),
+            "castle rights: invalid character 'x'"
+        );
+    }
+}
@@ -1,9 +1,8 @@
-use std::ffi::os_str::Display;
+use std::fmt::Display;
 use std::str::FromStr;
 use std::ops::{Index, IndexMut};
 
 use crate::error::Error;
-use crate::board::BB;
 
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
@@ -54,6 +53,19 @@ impl File {
         self as u8
     }
 
+    pub const fn to_char(self) -> char {
+        match self {
+            File::A => 'a',
+            File::B => 'b',
+            File::C => 'c',
+            File::D => 'd',
+            File::E => 'e',
+            File::F => 'f',
+            File::G => 'g',
+            File::H => 'h'
+        }
+    }
+
     pub const fn right(self) -> Option<File> {
         match self {
             File::A => Some(File::B),
@@ -100,7 +112,7 @@ impl FromStr for File {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() != 1 {
-            return Err(Error::InvalidFileCount);
+            return Err(Error::InvalidFile);
         }
         let c = s.chars().next().unwrap();
         File::from_char(c).ok_or(Error::InvalidFile)
@@ -109,17 +121,7 @@ impl FromStr for File {
 
 impl Display for File {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let c = match self {
-            File::A => 'a',
-            File::B => 'b',
-            File::C => 'c',
-            File::D => 'd',
-            File::E => 'e',
-            File::F => 'f',
-            File::G => 'g',
-            File::H => 'h'
-        };
-        write!(f, "{}", c)
+        write!(f, "{}", self.to_char())
     }
 }
 impl<T> Index<File> for [T] {
@@ -180,7 +182,7 @@ mod tests {
     #[test]
     fn to_u8() {
         for i in 0..File::COUNT {
-            assert_eq!(File::VARIANTS[i as usize].to_u8(), i as u8);
+            assert_eq!(File::VARIANTS[i].to_u8(), i as u8);
         }
     }
 
@@ -206,12 +208,11 @@ mod tests {
         assert_eq!(File::F.left(), Some(File::E));
         assert_eq!(File::G.left(), Some(File::F));
         assert_eq!(File::H.left(), Some(File::G));
-        assert_eq!(File::H.left(), Some(File::H));
     }
 
     #[test]
     fn offset() {
-        assert_eq!(File::A.offset(5),  Some(File::E));
+        assert_eq!(File::A.offset(5),  Some(File::F));
         assert_eq!(File::G.offset(-4), Some(File::C));
         assert_eq!(File::H.offset(2),  None);
         assert_eq!(File::B.offset(-2), None);
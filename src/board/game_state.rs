@@ -0,0 +1,228 @@
+use crate::board::{BB, Board, Move, PieceType, UndoInfo};
+
+/// A board together with the moves that led to it, for the draw rules that
+/// depend on the game's history.
+#[derive(Debug, Clone)]
+pub struct GameState {
+    board: Board,
+    moves: Vec<(Move, UndoInfo)>,
+    hashes: Vec<u64>
+}
+
+impl GameState {
+    pub fn new(board: Board) -> Self {
+        let hashes = vec![board.hash()];
+        GameState { board, moves: Vec::new(), hashes }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The moves played since the starting position, oldest first.
+    pub fn moves(&self) -> impl ExactSizeIterator<Item = Move> + '_ {
+        self.moves.iter().map(|&(m, _)| m)
+    }
+
+    pub fn make_move(&mut self, m: Move) {
+        let undo = self.board.make_move(m);
+        self.moves.push((m, undo));
+        self.hashes.push(self.board.hash());
+    }
+
+    /// Takes back the last move, returning it, or `None` at the starting position.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let (m, undo) = self.moves.pop()?;
+        self.board.unmake_move(m, undo);
+        self.hashes.pop();
+        Some(m)
+    }
+
+    /// How many times the current position has occurred, including now.
+    /// Only positions since the last capture or pawn move can repeat it.
+    pub fn repetition_count(&self) -> usize {
+        let current = self.board.hash();
+        let reversible = (self.board.halfmove_clock() as usize).min(self.hashes.len() - 1);
+        self.hashes
+            .iter()
+            .rev()
+            .take(reversible + 1)
+            .step_by(2)
+            .filter(|&&hash| hash == current)
+            .count()
+    }
+
+    /// Fifty moves by each side without a capture or pawn move. A draw the
+    /// players may claim, unless the last move delivered mate.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.board.halfmove_clock() >= 100 && !self.board.is_checkmate()
+    }
+
+    /// Seventy-five moves by each side without a capture or pawn move,
+    /// which ends the game automatically unless the last move delivered mate.
+    pub fn is_seventy_five_move_draw(&self) -> bool {
+        self.board.halfmove_clock() >= 150 && !self.board.is_checkmate()
+    }
+
+    /// The current position has occurred three times: a claimable draw.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// The current position has occurred five times: an automatic draw.
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetition_count() >= 5
+    }
+
+    pub fn is_insufficient_material(&self) -> bool {
+        self.board.is_insufficient_material()
+    }
+
+    /// A draw either player may claim but which does not end the game by itself.
+    pub fn is_claimable_draw(&self) -> bool {
+        self.is_fifty_move_draw() || self.is_threefold_repetition()
+    }
+
+    /// A draw that ends the game without a claim.
+    pub fn is_automatic_draw(&self) -> bool {
+        self.is_seventy_five_move_draw()
+            || self.is_fivefold_repetition()
+            || self.is_insufficient_material()
+            || self.board.is_stalemate()
+    }
+}
+
+impl Board {
+    /// Neither side can possibly checkmate: bare kings, a single minor
+    /// piece, or only bishops that all stand on squares of one color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.piece_type_bbs[PieceType::Pawn]
+            | self.piece_type_bbs[PieceType::Rook]
+            | self.piece_type_bbs[PieceType::Queen];
+        if heavy != BB::EMPTY {
+            return false;
+        }
+
+        let knights = self.piece_type_bbs[PieceType::Knight];
+        let bishops = self.piece_type_bbs[PieceType::Bishop];
+        match (knights.count(), bishops.count()) {
+            (0, _) => bishops & BB::DARK_SQUARES == BB::EMPTY || bishops & BB::LIGHT_SQUARES == BB::EMPTY,
+            (1, 0) => true,
+            _ => false
+        }
+    }
+}
+
+impl From<Board> for GameState {
+    fn from(board: Board) -> Self {
+        GameState::new(board)
+    }
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::new(Board::from_fen(Board::START_FEN).expect("start position is valid"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(state: &mut GameState, moves: &[&str]) {
+        for s in moves {
+            let m = state.board().parse_uci_move(s, false).unwrap();
+            state.make_move(m);
+        }
+    }
+
+    const KNIGHT_SHUFFLE: [&str; 4] = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    #[test]
+    fn threefold_and_fivefold_repetition() {
+        let mut state = GameState::default();
+        assert_eq!(state.repetition_count(), 1);
+
+        play(&mut state, &KNIGHT_SHUFFLE);
+        assert_eq!(state.repetition_count(), 2);
+        assert!(!state.is_threefold_repetition());
+
+        play(&mut state, &KNIGHT_SHUFFLE);
+        assert!(state.is_threefold_repetition());
+        assert!(state.is_claimable_draw());
+        assert!(!state.is_fivefold_repetition());
+
+        play(&mut state, &KNIGHT_SHUFFLE);
+        play(&mut state, &KNIGHT_SHUFFLE);
+        assert_eq!(state.repetition_count(), 5);
+        assert!(state.is_fivefold_repetition());
+        assert!(state.is_automatic_draw());
+
+        state.unmake_move();
+        assert_eq!(state.repetition_count(), 4);
+        assert_eq!(state.moves().len(), 15);
+    }
+
+    #[test]
+    fn irreversible_move_resets_repetition() {
+        let mut state = GameState::default();
+        play(&mut state, &KNIGHT_SHUFFLE);
+        play(&mut state, &["e2e4", "e7e5"]);
+        play(&mut state, &KNIGHT_SHUFFLE);
+        assert_eq!(state.repetition_count(), 2);
+    }
+
+    #[test]
+    fn fifty_and_seventy_five_move_rules() {
+        let state = GameState::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap());
+        assert!(!state.is_fifty_move_draw());
+
+        let state = GameState::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap());
+        assert!(state.is_fifty_move_draw());
+        assert!(state.is_claimable_draw());
+        assert!(!state.is_seventy_five_move_draw());
+        assert!(!state.is_automatic_draw());
+
+        let state = GameState::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 105").unwrap());
+        assert!(state.is_seventy_five_move_draw());
+        assert!(state.is_automatic_draw());
+
+        let mate = GameState::new(Board::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 105").unwrap());
+        assert!(!mate.is_fifty_move_draw());
+        assert!(!mate.is_seventy_five_move_draw());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let drawn = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/1B6/B1B1K3 w - - 0 1",
+        ];
+        for fen in drawn {
+            assert!(Board::from_fen(fen).unwrap().is_insufficient_material(), "{}", fen);
+        }
+
+        let playable = [
+            Board::START_FEN,
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3RK3 w - - 0 1",
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/2N1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+        ];
+        for fen in playable {
+            assert!(!Board::from_fen(fen).unwrap().is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn stalemate_is_an_automatic_draw() {
+        let state = GameState::new(Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap());
+        assert!(state.is_automatic_draw());
+        assert!(!state.is_claimable_draw());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::board::{Board, CastleRights, Color, Move, Piece, PieceType, SQ, zobrist};
+
+/// The parts of a position that cannot be recovered from the move alone,
+/// saved by `Board::make_move` so `Board::unmake_move` can restore them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoInfo {
+    captured: Option<Piece>,
+    castle_rights: CastleRights,
+    en_passant: Option<SQ>,
+    halfmove_clock: u8,
+    fullmove_number: u16,
+    hash: u64
+}
+
+impl UndoInfo {
+    pub fn captured(self) -> Option<Piece> {
+        self.captured
+    }
+}
+
+impl Board {
+    /// Applies `m` to the board. The move is assumed to be at least
+    /// pseudo-legal in the current position and flagged as move generation
+    /// flags it.
+    pub fn make_move(&mut self, m: Move) -> UndoInfo {
+        let us = self.side_to_move;
+        let from = m.origin_sq();
+        let to = m.target_sq();
+        let piece = self.pieces[from].expect("make_move called without a piece on the origin square");
+        debug_assert_eq!(piece.color(), us);
+
+        let mut undo = UndoInfo {
+            captured: if m.is_castle() { None } else { self.pieces[to] },
+            castle_rights: self.castle_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash
+        };
+
+        self.hash ^= self.en_passant_hash();
+        self.en_passant = None;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+
+        if let Some(captured) = undo.captured {
+            debug_assert_ne!(captured.color(), us);
+            self.remove_piece(to);
+            self.halfmove_clock = 0;
+        }
+
+        if piece.type_of() == PieceType::Pawn {
+            self.halfmove_clock = 0;
+
+            if m.is_en_passant() {
+                let captured_sq = Board::en_passant_capture_sq(to, us);
+                undo.captured = self.pieces[captured_sq];
+                self.remove_piece(captured_sq);
+            } else if m.is_double_push() {
+                self.en_passant = from.forward(us);
+            }
+        }
+
+        if m.is_castle() {
+            let (king_to, rook_to) = m.castle_destinations();
+            self.move_castling_pieces(us, (from, to), (king_to, rook_to));
+        } else {
+            self.move_piece(from, to);
+        }
+
+        if let Some(promotion) = m.promotion() {
+            self.remove_piece(to);
+            self.add_piece(to, Piece::new(us, promotion));
+        }
+
+        self.castle_rights &= self.castle_masks[from] & self.castle_masks[to];
+
+        if us == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
+        self.side_to_move = !us;
+
+        self.hash ^= zobrist::castle_key(undo.castle_rights) ^ zobrist::castle_key(self.castle_rights);
+        self.hash ^= self.en_passant_hash();
+        self.hash ^= zobrist::side_key();
+        debug_assert_eq!(self.hash, self.compute_hash_from_scratch());
+
+        undo
+    }
+
+    /// Reverts `m`, which must be the last move made with `make_move`.
+    pub fn unmake_move(&mut self, m: Move, undo: UndoInfo) {
+        let us = !self.side_to_move;
+        let from = m.origin_sq();
+        let to = m.target_sq();
+
+        self.side_to_move = us;
+
+        if m.promotion().is_some() {
+            self.remove_piece(to);
+            self.add_piece(to, Piece::new(us, PieceType::Pawn));
+        }
+
+        if m.is_castle() {
+            let (king_to, rook_to) = m.castle_destinations();
+            self.move_castling_pieces(us, (king_to, rook_to), (from, to));
+        } else {
+            self.move_piece(to, from);
+        }
+
+        if let Some(captured) = undo.captured {
+            let captured_sq = if m.is_en_passant() { Board::en_passant_capture_sq(to, us) } else { to };
+            self.add_piece(captured_sq, captured);
+        }
+
+        self.castle_rights = undo.castle_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+    }
+
+    /// Passes the turn without moving, for null-move pruning. The halfmove
+    /// clock restarts, so no repetition is looked for across the pass.
+    pub fn make_null_move(&mut self) -> UndoInfo {
+        debug_assert!(!self.in_check());
+        let undo = UndoInfo {
+            captured: None,
+            castle_rights: self.castle_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash
+        };
+
+        self.hash ^= self.en_passant_hash();
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        if self.side_to_move == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
+        self.side_to_move = !self.side_to_move;
+        self.hash ^= zobrist::side_key();
+        debug_assert_eq!(self.hash, self.compute_hash_from_scratch());
+
+        undo
+    }
+
+    /// Reverts the last `make_null_move`.
+    pub fn unmake_null_move(&mut self, undo: UndoInfo) {
+        self.side_to_move = !self.side_to_move;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+    }
+
+    fn move_piece(&mut self, from: SQ, to: SQ) {
+        let piece = self.pieces[from].expect("move_piece called without a piece on the origin square");
+        self.remove_piece(from);
+        self.add_piece(to, piece);
+    }
+
+    /// Moves `us`'s castling king and rook from the squares in `from` to
+    /// those in `to`, each given as (king, rook). Both leave the board
+    /// before either lands, since in Chess960 each may end on the other's
+    /// square, or on its own.
+    fn move_castling_pieces(&mut self, us: Color, from: (SQ, SQ), to: (SQ, SQ)) {
+        self.remove_piece(from.0);
+        self.remove_piece(from.1);
+        self.add_piece(to.0, Piece::new(us, PieceType::King));
+        self.add_piece(to.1, Piece::new(us, PieceType::Rook));
+    }
+
+    /// The square of the pawn taken by an en passant capture landing on `to`.
+    fn en_passant_capture_sq(to: SQ, us: Color) -> SQ {
+        to.backward(us).expect("en passant target square is never on the back rank")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveFlag;
+
+    fn assert_make_unmake(fen: &str, m: Move, expected_fen: &str) {
+        let mut board = Board::from_fen(fen).unwrap();
+        let original = board.clone();
+
+        let undo = board.make_move(m);
+        assert_eq!(board.to_fen(), expected_fen);
+        assert_eq!(board.hash(), Board::from_fen(expected_fen).unwrap().hash());
+
+        board.unmake_move(m, undo);
+        assert_eq!(board, original);
+    }
+
+    #[test]
+    fn quiet_moves_and_double_push() {
+        assert_make_unmake(
+            Board::START_FEN,
+            Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        assert_make_unmake(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            Move::new(SQ::G8, SQ::F6, MoveFlag::Quiet),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn capture_resets_halfmove_clock() {
+        assert_make_unmake(
+            "4k3/8/8/3p4/8/8/3Q4/4K3 w - - 7 30",
+            Move::new(SQ::D2, SQ::D5, MoveFlag::Capture),
+            "4k3/8/8/3Q4/8/8/8/4K3 b - - 0 30"
+        );
+    }
+
+    #[test]
+    fn fullmove_number_saturates() {
+        assert_make_unmake(
+            "4k3/8/8/8/8/8/8/4K3 b - - 0 65535",
+            Move::new(SQ::E8, SQ::D8, MoveFlag::Quiet),
+            "3k4/8/8/8/8/8/8/4K3 w - - 1 65535"
+        );
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        assert_make_unmake(
+            "4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 2",
+            Move::new(SQ::D5, SQ::C6, MoveFlag::EnPassant),
+            "4k3/8/2P5/8/8/8/8/4K3 b - - 0 2"
+        );
+        assert_make_unmake(
+            "4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 2",
+            Move::new(SQ::E4, SQ::D3, MoveFlag::EnPassant),
+            "4k3/8/8/8/8/3p4/8/4K3 w - - 0 3"
+        );
+    }
+
+    #[test]
+    fn promotions() {
+        assert_make_unmake(
+            "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1",
+            Move::new(SQ::B7, SQ::B8, MoveFlag::QueenPromotion),
+            "1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1"
+        );
+        assert_make_unmake(
+            "r3k3/1P6/8/8/8/8/8/4K3 w q - 0 1",
+            Move::new(SQ::B7, SQ::A8, MoveFlag::KnightPromotionCapture),
+            "N3k3/8/8/8/8/8/8/4K3 b - - 0 1"
+        );
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_make_unmake(fen, Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert_make_unmake(fen, Move::new(SQ::E1, SQ::A1, MoveFlag::QueenCastle), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_make_unmake(fen, Move::new(SQ::E8, SQ::H8, MoveFlag::KingCastle), "r4rk1/8/8/8/8/8/8/R3K2R w KQ - 1 2");
+        assert_make_unmake(fen, Move::new(SQ::E8, SQ::A8, MoveFlag::QueenCastle), "2kr3r/8/8/8/8/8/8/R3K2R w KQ - 1 2");
+    }
+
+    #[test]
+    fn rook_moves_and_captures_clear_castle_rights() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_make_unmake(fen, Move::new(SQ::H1, SQ::H5, MoveFlag::Quiet), "r3k2r/8/8/7R/8/8/8/R3K3 b Qkq - 1 1");
+        assert_make_unmake(fen, Move::new(SQ::A1, SQ::A8, MoveFlag::Capture), "R3k2r/8/8/8/8/8/8/4K2R b Kk - 0 1");
+    }
+
+    #[test]
+    fn only_the_castling_rook_carries_the_right() {
+        let fen = "4k3/8/8/8/8/8/8/R1R1K3 w C - 0 1";
+        assert_make_unmake(fen, Move::new(SQ::A1, SQ::A2, MoveFlag::Quiet), "4k3/8/8/8/8/8/R7/2R1K3 b Q - 1 1");
+        assert_make_unmake(fen, Move::new(SQ::C1, SQ::C2, MoveFlag::Quiet), "4k3/8/8/8/8/8/2R5/R3K3 b - - 1 1");
+        assert_make_unmake(fen, Move::new(SQ::E1, SQ::D1, MoveFlag::Quiet), "4k3/8/8/8/8/8/8/R1RK4 b - - 1 1");
+    }
+
+    #[test]
+    fn null_move_passes_the_turn() {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 40";
+        let mut board = Board::from_fen(fen).unwrap();
+        let original = board.clone();
+
+        let undo = board.make_null_move();
+        assert_eq!(board.to_fen(), "4k3/8/8/3pP3/8/8/8/4K3 b - - 0 40");
+        assert_eq!(board.hash(), Board::from_fen(&board.to_fen()).unwrap().hash());
+
+        board.unmake_null_move(undo);
+        assert_eq!(board, original);
+    }
+
+    #[test]
+    fn make_unmake_sequence_restores_start_position() {
+        let moves = [
+            Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush),
+            Move::new(SQ::D7, SQ::D5, MoveFlag::DoublePush),
+            Move::new(SQ::E4, SQ::D5, MoveFlag::Capture),
+            Move::new(SQ::G8, SQ::F6, MoveFlag::Quiet),
+            Move::new(SQ::F1, SQ::B5, MoveFlag::Quiet),
+            Move::new(SQ::C7, SQ::C6, MoveFlag::Quiet),
+            Move::new(SQ::G1, SQ::F3, MoveFlag::Quiet),
+            Move::new(SQ::C6, SQ::B5, MoveFlag::Capture),
+            Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle),
+        ];
+
+        let mut board = Board::from_fen(Board::START_FEN).unwrap();
+        let mut undos = Vec::new();
+        for m in moves {
+            undos.push(board.make_move(m));
+        }
+        assert_eq!(board.to_fen(), "rnbqkb1r/pp2pppp/5n2/1p1P4/8/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 5");
+
+        for (m, undo) in moves.iter().rev().zip(undos.into_iter().rev()) {
+            board.unmake_move(*m, undo);
+        }
+        assert_eq!(board, Board::from_fen(Board::START_FEN).unwrap());
+    }
+}
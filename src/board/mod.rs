@@ -8,6 +8,14 @@ pub mod chess_move;
 pub mod castle_rights;
 pub mod bitboard;
 pub mod attacks;
+pub mod fen;
+pub mod make_move;
+pub mod movegen;
+pub mod zobrist;
+pub mod game_state;
+pub mod san;
+pub mod see;
+pub mod chess960;
 mod magic;
 
 pub use direction::Direction;
@@ -16,31 +24,36 @@ pub use rank::Rank;
 pub use file::File;
 pub use square::SQ;
 pub use piece::{Piece, PieceType};
-pub use chess_move::Move;
-pub use castle_rights::CastleRights;
+pub use chess_move::{Move, MoveFlag, MoveList, MoveListIntoIter, MoveParseError};
+pub use castle_rights::{CastleRights, CastleSide};
 pub use bitboard::BB;
+pub use fen::{FenError, FenField};
+pub use make_move::UndoInfo;
+pub use game_state::GameState;
+pub use san::SanError;
+pub use see::PIECE_VALUES;
 pub use attacks::{
-    pawn_attacks, knight_attacks, bishop_attacks, rook_attacks, queen_attacks, king_attacks
+    pawn_attacks, knight_attacks, bishop_attacks, rook_attacks, queen_attacks, king_attacks,
+    between, line
 };
 
-pub enum FenError {
-    InvalidTokenCount,
-    InvalidRankCount,
-    InvalidSideToMove,
-    InvalidCastleRights,
-
-}
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pieces: [Option<Piece>; SQ::COUNT],
     occupied_bbs: [BB; Color::COUNT],
     piece_type_bbs: [BB; PieceType::COUNT],
-    castle_rights: [CastleRights; Color::COUNT],
+    side_to_move: Color,
+    castle_rights: CastleRights,
+    castle_rook_files: [[File; CastleSide::COUNT]; Color::COUNT],
+    castle_masks: [CastleRights; SQ::COUNT],
     en_passant: Option<SQ>,
     halfmove_clock: u8,
-    fullmove_number: u16
+    fullmove_number: u16,
+    hash: u64
 }
 
 impl Board {
+    pub const START_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
     pub fn occupied_bb(&self) -> BB {
         self.occupied_bbs[Color::White] | self.occupied_bbs[Color::Black]
@@ -50,6 +63,10 @@ impl Board {
         self.occupied_bbs[color]
     }
 
+    pub fn piece_type_bb(&self, piece_type: PieceType) -> BB {
+        self.piece_type_bbs[piece_type]
+    }
+
     pub fn piece_bb(&self, piece: Piece) -> BB {
         self.color_bb(piece.color()) & self.piece_type_bbs[piece.type_of()]
     }
@@ -61,11 +78,106 @@ impl Board {
     pub fn piece_count(&self, piece: Piece) -> u8 {
         self.piece_bb(piece).count()
     }
+
+    /// Whether `color` has anything besides its king and pawns. Without
+    /// such pieces zugzwang is common, so passing the turn is no longer a
+    /// safe guess at the worst case.
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let kings_and_pawns = self.piece_type_bbs[PieceType::King] | self.piece_type_bbs[PieceType::Pawn];
+        self.color_bb(color) & !kings_and_pawns != BB::EMPTY
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn castle_rights(&self) -> CastleRights {
+        self.castle_rights
+    }
+
+    /// The square of the rook `color` castles with towards `side`. Outside
+    /// Chess960 this is always a corner.
+    pub fn castle_rook_sq(&self, color: Color, side: CastleSide) -> SQ {
+        SQ::from_coords(self.castle_rook_files[color][side], Rank::back_rank(color))
+    }
+
+    pub fn en_passant(&self) -> Option<SQ> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u8 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// The Zobrist hash of the position, maintained incrementally.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recomputes the Zobrist hash from the board contents, for checking
+    /// the incremental hash.
+    pub fn compute_hash_from_scratch(&self) -> u64 {
+        let mut hash = 0;
+        for sq in self.occupied_bb() {
+            if let Some(piece) = self.pieces[sq] {
+                hash ^= zobrist::piece_key(piece, sq);
+            }
+        }
+
+        hash ^= zobrist::castle_key(self.castle_rights);
+
+        hash ^= self.en_passant_hash();
+
+        if self.side_to_move == Color::Black {
+            hash ^= zobrist::side_key();
+        }
+        hash
+    }
+
+    /// The en passant part of the hash. The square only counts when a pawn
+    /// of the side to move could capture onto it, so positions that differ
+    /// only by an unusable en passant square hash alike.
+    fn en_passant_hash(&self) -> u64 {
+        match self.capturable_en_passant() {
+            Some(ep) => zobrist::en_passant_key(ep.file()),
+            None => 0
+        }
+    }
+
+    /// The en passant square, if a pawn of the side to move attacks it.
+    pub(crate) fn capturable_en_passant(&self) -> Option<SQ> {
+        let ep = self.en_passant?;
+        let us = self.side_to_move;
+        let capturers = pawn_attacks(ep, !us) & self.piece_bb(Piece::new(us, PieceType::Pawn));
+        (capturers != BB::EMPTY).then_some(ep)
+    }
+
+    /// Rebuilds the table of rights each square keeps when a move starts or
+    /// ends on it: a king square clears both of its color's rights and a
+    /// castling rook's square the right it belongs to. Making a move then
+    /// takes one AND per square.
+    fn update_castle_masks(&mut self) {
+        self.castle_masks = [CastleRights::ALL; SQ::COUNT];
+        for color in Color::VARIANTS {
+            let king_sq = self.king_sq(color);
+            self.castle_masks[king_sq].remove(CastleRights::both(color));
+            for side in CastleSide::VARIANTS {
+                let rook_sq = self.castle_rook_sq(color, side);
+                self.castle_masks[rook_sq].remove(CastleRights::new(color, side));
+            }
+        }
+    }
+
     fn add_piece(&mut self, sq: SQ, piece: Piece) {
         debug_assert_eq!(self.pieces[sq], None);
         self.pieces[sq] = Some(piece);
         self.occupied_bbs[piece.color()]     |= BB::from_sq(sq);
         self.piece_type_bbs[piece.type_of()] |= BB::from_sq(sq);
+        self.hash ^= zobrist::piece_key(piece, sq);
     }
 
     fn remove_piece(&mut self, sq: SQ) {
@@ -73,63 +185,25 @@ impl Board {
             self.occupied_bbs[piece.color()]     &= !BB::from_sq(sq);
             self.piece_type_bbs[piece.type_of()] &= !BB::from_sq(sq);
             self.pieces[sq] = None;
+            self.hash ^= zobrist::piece_key(piece, sq);
         }
     }
-
-    fn parse_fen_position(fen: &str, board: &mut Board) -> Result<(), FenError> {
-        let parts: Vec<&str> = fen.split('/').collect();
-        if parts.len() != Rank::COUNT {
-            return Err(FenError::InvalidRankCount);
-        }
-        let rank = Rank::Eighth;
-        loop {
-            let mut file = File::A;
-            for c in fen.chars() {
-                if c == '/' {
-                    if file != File::H {return Err(FenError::InvalidRankCount);}
-                    rank = match rank.down() {
-                        Some(r) => r,
-                        None => return Err(Error::InvalidFen)
-                    };
-                    break;
-                } else if c.is_digit(10) {
-                    let Some(digit) = c.to_digit(10) else { 
-                        return Err(FenError::InvalidFen); 
-                    };
-
-                    if digit == 0 || digit == 9 {
-                        return Err(FenError::InvalidFen);
-                    }
-
-                    file = File::from_u8(file.to_u8() + digit as u8);
-                } else {
-                    let Some(piece) = Piece::from_char(c) else {
-                        return Err(FenError::InvalidFileCount);
-                    };
-                    
-                    board.add_piece(SQ::from_coords(file, rank), piece);
-                    file = File::from_u8(file.to_u8() + 1);
-                }
-            }
-        }
-
-        Ok(())
-    }
 }
 
-
-
 impl Default for Board {
     fn default() -> Self {
         Board {
             pieces: [None; SQ::COUNT],
             occupied_bbs: [BB(0); Color::COUNT],
-
             piece_type_bbs: [BB(0); PieceType::COUNT],
-            castle_rights: [CastleRights::empty(); Color::COUNT],
+            side_to_move: Color::White,
+            castle_rights: CastleRights::NONE,
+            castle_rook_files: [[File::H, File::A]; Color::COUNT],
+            castle_masks: [CastleRights::ALL; SQ::COUNT],
             en_passant: None,
             halfmove_clock: 0,
-            fullmove_number: 1
+            fullmove_number: 1,
+            hash: 0
         }
     }
 }
@@ -138,14 +212,71 @@ impl Default for Board {
 mod tests {
     use super::*;
 
+    const FEN_CORPUS: [&str; 8] = [
+        Board::START_FEN,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "4k3/8/8/2pP4/8/8/8/4K3 w - c6 42 117",
+    ];
+
     #[test]
-    fn from_fen() {}
+    fn from_fen() {
+        let board = Board::from_fen(Board::START_FEN).unwrap();
+        assert_eq!(board.side_to_move(), Color::White);
+        assert_eq!(board.castle_rights(), CastleRights::ALL);
+        assert_eq!(board.castle_rook_sq(Color::White, CastleSide::King), SQ::H1);
+        assert_eq!(board.castle_rook_sq(Color::Black, CastleSide::Queen), SQ::A8);
+        assert_eq!(board.en_passant(), None);
+        assert_eq!(board.halfmove_clock(), 0);
+        assert_eq!(board.fullmove_number(), 1);
+        assert_eq!(board.occupied_bb(), BB(0xFFFF00000000FFFF));
+        assert_eq!(board.piece_on(SQ::E1), Some(Piece::WHITE_KING));
+        assert_eq!(board.piece_on(SQ::D8), Some(Piece::BLACK_QUEEN));
+        assert_eq!(board.piece_count(Piece::WHITE_PAWN), 8);
+
+        let board = Board::from_fen(FEN_CORPUS[7]).unwrap();
+        assert_eq!(board.side_to_move(), Color::White);
+        assert_eq!(board.castle_rights(), CastleRights::NONE);
+        assert_eq!(board.en_passant(), Some(SQ::C6));
+        assert_eq!(board.halfmove_clock(), 42);
+        assert_eq!(board.fullmove_number(), 117);
+    }
 
     #[test]
-    fn to_fen() {}
+    fn from_fen_without_clocks() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(board.side_to_move(), Color::Black);
+        assert_eq!(board.halfmove_clock(), 0);
+        assert_eq!(board.fullmove_number(), 1);
+    }
 
     #[test]
-    fn piece_on() {}
+    fn to_fen() {
+        for fen in FEN_CORPUS {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn piece_on() {
+        let board = Board::from_fen(FEN_CORPUS[3]).unwrap();
+        assert_eq!(board.piece_on(SQ::A5), Some(Piece::WHITE_KING));
+        assert_eq!(board.piece_on(SQ::H4), Some(Piece::BLACK_KING));
+        assert_eq!(board.piece_on(SQ::B4), Some(Piece::WHITE_ROOK));
+        assert_eq!(board.piece_on(SQ::C7), Some(Piece::BLACK_PAWN));
+        assert_eq!(board.piece_on(SQ::E4), None);
+    }
+
+    #[test]
+    fn has_non_pawn_material() {
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/5PPP/4K2N w - - 0 1").unwrap();
+        assert!(board.has_non_pawn_material(Color::White));
+        assert!(!board.has_non_pawn_material(Color::Black));
+    }
 
     #[test]
     fn add_piece() {
@@ -159,11 +290,34 @@ mod tests {
     #[test]
     fn remove_piece() {
         let mut board = Board::default();
+        let sq = SQ::D5;
 
         board.add_piece(sq, Piece::WHITE_KING);
         board.remove_piece(sq);
         assert_eq!(board.piece_on(sq), None);
-        assert_eq!(board.color_bbs[Color::White], BB(0));
-        assert_eq!(board.piece_type_bbs[PieceType::King] & BB::EMPTY.with_set(sq) != BB(0));
+        assert_eq!(board.occupied_bbs[Color::White], BB(0));
+        assert!(!board.piece_type_bbs[PieceType::King].is_set(sq));
+        assert_eq!(board.hash(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hash() {
+        for fen in FEN_CORPUS {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.hash(), board.compute_hash_from_scratch());
+        }
+
+        let white = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(white.hash() ^ black.hash(), zobrist::side_key());
+
+        // An en passant square no pawn can use does not change the hash.
+        let with_ep = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        let without_ep = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(with_ep.hash(), without_ep.hash());
+
+        let with_ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let without_ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(with_ep.hash() ^ without_ep.hash(), zobrist::en_passant_key(File::E));
+    }
+}
@@ -0,0 +1,638 @@
+use crate::board::{
+    Board, BB, CastleSide, Color, Move, MoveFlag, MoveList, Piece, PieceType, Rank, SQ,
+    pawn_attacks, knight_attacks, bishop_attacks, rook_attacks, king_attacks, between, line
+};
+
+const PROMOTION_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GenKind {
+    All,
+    /// Captures and queen promotions only.
+    Captures,
+    /// Everything `Captures` leaves out.
+    Quiets
+}
+
+impl Board {
+    /// Generates pseudo-legal moves: each one follows the movement rules of
+    /// its piece, but may leave the mover's king in check. Castling is only
+    /// generated when the king does not start in, pass through or land on
+    /// an attacked square, since that cannot be detected after the fact.
+    pub fn generate_moves(&self, list: &mut MoveList) {
+        self.generate(list, false, GenKind::All);
+    }
+
+    /// Generates strictly legal moves. Moves are filtered with checker and
+    /// pin bitboards rather than by making them.
+    pub fn generate_legal_moves(&self, list: &mut MoveList) {
+        self.generate(list, true, GenKind::All);
+    }
+
+    /// Generates the legal captures, including en passant, and the queen
+    /// promotions, whether or not they capture. Underpromotions are left out.
+    pub fn generate_captures(&self, list: &mut MoveList) {
+        self.generate(list, true, GenKind::Captures);
+    }
+
+    /// Generates the legal moves that `generate_captures` leaves out: moves
+    /// to empty squares other than queen promotions, and underpromotions
+    /// whether or not they capture.
+    pub fn generate_quiets(&self, list: &mut MoveList) {
+        self.generate(list, true, GenKind::Quiets);
+    }
+
+    pub fn legal_moves(&self) -> MoveList {
+        let mut list = MoveList::new();
+        self.generate_legal_moves(&mut list);
+        list
+    }
+
+    /// Enemy pieces giving check to the side to move.
+    pub fn checkers(&self) -> BB {
+        let us = self.side_to_move;
+        self.attackers_to(self.king_sq(us), self.occupied_bb()) & self.color_bb(!us)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.checkers() != BB::EMPTY
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.legal_moves().is_empty()
+    }
+
+    /// Pieces of either color attacking `sq`, with sliders blocked by
+    /// `occupied`. Passing an occupancy with pieces removed reveals the
+    /// attackers behind them.
+    pub fn attackers_to(&self, sq: SQ, occupied: BB) -> BB {
+        let bishops_queens = self.piece_type_bbs[PieceType::Bishop] | self.piece_type_bbs[PieceType::Queen];
+        let rooks_queens   = self.piece_type_bbs[PieceType::Rook]   | self.piece_type_bbs[PieceType::Queen];
+
+        (pawn_attacks(sq, Color::White) & self.piece_bb(Piece::BLACK_PAWN))
+            | (pawn_attacks(sq, Color::Black) & self.piece_bb(Piece::WHITE_PAWN))
+            | (knight_attacks(sq) & self.piece_type_bbs[PieceType::Knight])
+            | (king_attacks(sq) & self.piece_type_bbs[PieceType::King])
+            | (bishop_attacks(sq, occupied) & bishops_queens)
+            | (rook_attacks(sq, occupied) & rooks_queens)
+    }
+
+    fn generate(&self, list: &mut MoveList, legal: bool, kind: GenKind) {
+        let us = self.side_to_move;
+        let them = !us;
+        let occupied = self.occupied_bb();
+        let king_sq = self.king_sq(us);
+
+        let (checkers, pinned) = if legal {
+            (self.checkers(), self.pinned(us))
+        } else {
+            (BB::EMPTY, BB::EMPTY)
+        };
+
+        // The king may not step onto a square that its own body shields
+        // from a slider, so it is removed from the occupancy first.
+        let without_king = occupied & !BB::from_sq(king_sq);
+        let king_targets = match kind {
+            GenKind::All      => !self.color_bb(us),
+            GenKind::Captures => self.color_bb(them),
+            GenKind::Quiets   => !occupied
+        };
+        for to in king_attacks(king_sq) & king_targets {
+            if !legal || !self.is_attacked(to, them, without_king) {
+                list.push(Move::new(king_sq, to, self.capture_flag(to)));
+            }
+        }
+
+        if checkers.count() > 1 {
+            return;
+        }
+
+        let mut target_mask = !self.color_bb(us);
+        if let Some(checker) = checkers.lsb_sq() {
+            target_mask &= between(king_sq, checker) | checkers;
+        } else if kind != GenKind::Captures {
+            self.generate_castles(list, king_sq);
+        }
+
+        self.generate_pawn_moves(list, king_sq, target_mask, pinned, legal, kind);
+
+        match kind {
+            GenKind::All      => {},
+            GenKind::Captures => target_mask &= self.color_bb(them),
+            GenKind::Quiets   => target_mask &= !occupied
+        }
+
+        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            for from in self.piece_bb(Piece::new(us, piece_type)) {
+                let mut targets = self.piece_attacks(piece_type, from, occupied) & target_mask;
+                if pinned.is_set(from) {
+                    targets &= line(king_sq, from);
+                }
+
+                for to in targets {
+                    list.push(Move::new(from, to, self.capture_flag(to)));
+                }
+            }
+        }
+    }
+
+    fn generate_pawn_moves(
+        &self,
+        list: &mut MoveList,
+        king_sq: SQ,
+        target_mask: BB,
+        pinned: BB,
+        legal: bool,
+        kind: GenKind
+    ) {
+        let us = self.side_to_move;
+        let them = !us;
+        let start_rank = match us {
+            Color::White => Rank::Second,
+            Color::Black => Rank::Seventh
+        };
+
+        for from in self.piece_bb(Piece::new(us, PieceType::Pawn)) {
+            let mask = if pinned.is_set(from) {
+                target_mask & line(king_sq, from)
+            } else {
+                target_mask
+            };
+
+            if let Some(single) = from.forward(us) && self.pieces[single].is_none() {
+                let promotes = is_promotion_rank(single.rank());
+                if mask.is_set(single) && (kind != GenKind::Captures || promotes) {
+                    Board::push_pawn_move(list, from, single, false, kind);
+                }
+
+                if kind != GenKind::Captures
+                    && from.rank() == start_rank
+                    && let Some(double) = single.forward(us)
+                    && self.pieces[double].is_none()
+                    && mask.is_set(double)
+                {
+                    list.push(Move::new(from, double, MoveFlag::DoublePush));
+                }
+            }
+
+            for to in pawn_attacks(from, us) & self.color_bb(them) & mask {
+                // Of the captures, only underpromotions count as quiet.
+                if kind == GenKind::Quiets && !is_promotion_rank(to.rank()) {
+                    continue;
+                }
+                Board::push_pawn_move(list, from, to, true, kind);
+            }
+
+            if kind != GenKind::Quiets
+                && let Some(ep) = self.en_passant
+                && pawn_attacks(from, us).is_set(ep)
+                && (!legal || self.is_legal_en_passant(from, ep, king_sq))
+            {
+                list.push(Move::new(from, ep, MoveFlag::EnPassant));
+            }
+        }
+    }
+
+    fn push_pawn_move(list: &mut MoveList, from: SQ, to: SQ, capture: bool, kind: GenKind) {
+        if is_promotion_rank(to.rank()) {
+            let promotions = match kind {
+                GenKind::All      => &PROMOTION_TYPES[..],
+                GenKind::Captures => &PROMOTION_TYPES[..1],
+                GenKind::Quiets   => &PROMOTION_TYPES[1..]
+            };
+            for &promotion in promotions {
+                list.push(Move::new(from, to, MoveFlag::promotion(promotion, capture)));
+            }
+        } else {
+            list.push(Move::new(from, to, if capture { MoveFlag::Capture } else { MoveFlag::Quiet }));
+        }
+    }
+
+    /// Whether `m` is legal here, checked without generating every move.
+    /// Moves remembered from other positions, such as the transposition
+    /// table's or killer moves, must pass this before they are played.
+    pub fn is_legal(&self, m: Move) -> bool {
+        let us = self.side_to_move;
+        let them = !us;
+        let from = m.origin_sq();
+        let to = m.target_sq();
+        let Some(piece) = self.pieces[from] else {
+            return false;
+        };
+        if piece.color() != us {
+            return false;
+        }
+
+        let king_sq = self.king_sq(us);
+        if m.is_castle() {
+            let mut castles = MoveList::new();
+            if from == king_sq && !self.in_check() {
+                self.generate_castles(&mut castles, king_sq);
+            }
+            return castles.contains(&m);
+        }
+
+        if self.color_bb(us).is_set(to) {
+            return false;
+        }
+
+        let occupied = self.occupied_bb();
+        let promotes = piece.type_of() == PieceType::Pawn && is_promotion_rank(to.rank());
+        if promotes != m.is_promotion() || self.infer_move(from, to, m.promotion()) != m {
+            return false;
+        }
+
+        match piece.type_of() {
+            PieceType::Pawn => {
+                if Some(to) == self.en_passant && pawn_attacks(from, us).is_set(to) {
+                    return self.is_legal_en_passant(from, to, king_sq);
+                }
+
+                let mut reachable = pawn_attacks(from, us) & self.color_bb(them);
+                if let Some(single) = from.forward(us) && !occupied.is_set(single) {
+                    reachable |= BB::from_sq(single);
+                    let start_rank = if us == Color::White { Rank::Second } else { Rank::Seventh };
+                    if from.rank() == start_rank
+                        && let Some(double) = single.forward(us)
+                        && !occupied.is_set(double)
+                    {
+                        reachable |= BB::from_sq(double);
+                    }
+                }
+                if !reachable.is_set(to) {
+                    return false;
+                }
+            },
+            PieceType::King => {
+                return king_attacks(from).is_set(to) && !self.is_attacked(to, them, occupied & !BB::from_sq(from));
+            },
+            piece_type => {
+                if !self.piece_attacks(piece_type, from, occupied).is_set(to) {
+                    return false;
+                }
+            }
+        }
+
+        let checkers = self.checkers();
+        if let Some(checker) = checkers.lsb_sq()
+            && (checkers.count() > 1 || !(between(king_sq, checker) | checkers).is_set(to))
+        {
+            return false;
+        }
+        !self.pinned(us).is_set(from) || line(king_sq, from).is_set(to)
+    }
+
+    /// En passant removes two pieces from the king's surroundings at once,
+    /// so pins and check evasion are verified against the resulting board.
+    fn is_legal_en_passant(&self, from: SQ, ep: SQ, king_sq: SQ) -> bool {
+        let us = self.side_to_move;
+        let captured_sq = ep.backward(us).expect("en passant target square is never on the back rank");
+        let occupied = (self.occupied_bb() & !BB::from_sq(from) & !BB::from_sq(captured_sq)) | BB::from_sq(ep);
+        let attackers = self.attackers_to(king_sq, occupied) & self.color_bb(!us) & !BB::from_sq(captured_sq);
+        attackers == BB::EMPTY
+    }
+
+    /// Castles as in Chess960, which covers standard chess: the king and
+    /// rook may start on any files, and end on the g- and f-files or the c-
+    /// and d-files. Every square either crosses or lands on must be empty
+    /// except for the two of them, and no square the king crosses may be
+    /// attacked once the rook no longer shields it.
+    fn generate_castles(&self, list: &mut MoveList, king_sq: SQ) {
+        let us = self.side_to_move;
+        let them = !us;
+        if king_sq.rank() != Rank::back_rank(us) {
+            return;
+        }
+
+        let sides = [
+            (CastleSide::King,  MoveFlag::KingCastle),
+            (CastleSide::Queen, MoveFlag::QueenCastle),
+        ];
+
+        for (side, flag) in sides {
+            let rook_sq = self.castle_rook_sq(us, side);
+            if !self.castle_rights.has(us, side) || self.pieces[rook_sq] != Some(Piece::new(us, PieceType::Rook)) {
+                continue;
+            }
+
+            let m = Move::new(king_sq, rook_sq, flag);
+            let (king_to, rook_to) = m.castle_destinations();
+            let occupied = self.occupied_bb() & !BB::from_sq(king_sq) & !BB::from_sq(rook_sq);
+            let king_path = between(king_sq, king_to) | BB::from_sq(king_sq) | BB::from_sq(king_to);
+            let rook_path = between(rook_sq, rook_to) | BB::from_sq(rook_to);
+            if (king_path | rook_path) & occupied != BB::EMPTY {
+                continue;
+            }
+
+            if king_path.into_iter().any(|sq| self.is_attacked(sq, them, occupied)) {
+                continue;
+            }
+
+            list.push(m);
+        }
+    }
+
+    fn capture_flag(&self, to: SQ) -> MoveFlag {
+        if self.pieces[to].is_some() { MoveFlag::Capture } else { MoveFlag::Quiet }
+    }
+
+    fn piece_attacks(&self, piece_type: PieceType, sq: SQ, occupied: BB) -> BB {
+        match piece_type {
+            PieceType::Pawn   => pawn_attacks(sq, self.side_to_move),
+            PieceType::Knight => knight_attacks(sq),
+            PieceType::Bishop => bishop_attacks(sq, occupied),
+            PieceType::Rook   => rook_attacks(sq, occupied),
+            PieceType::Queen  => bishop_attacks(sq, occupied) | rook_attacks(sq, occupied),
+            PieceType::King   => king_attacks(sq)
+        }
+    }
+
+    pub(crate) fn king_sq(&self, color: Color) -> SQ {
+        self.piece_bb(Piece::new(color, PieceType::King))
+            .lsb_sq()
+            .expect("every position has a king of each color")
+    }
+
+    fn is_attacked(&self, sq: SQ, by: Color, occupied: BB) -> bool {
+        self.attackers_to(sq, occupied) & self.color_bb(by) != BB::EMPTY
+    }
+
+    /// Pieces of `color` that are the only blocker between their king and an
+    /// enemy slider.
+    fn pinned(&self, color: Color) -> BB {
+        let king_sq = self.king_sq(color);
+        let them = self.color_bb(!color);
+        let bishops_queens = self.piece_type_bbs[PieceType::Bishop] | self.piece_type_bbs[PieceType::Queen];
+        let rooks_queens   = self.piece_type_bbs[PieceType::Rook]   | self.piece_type_bbs[PieceType::Queen];
+
+        let snipers = ((bishop_attacks(king_sq, them) & bishops_queens)
+            | (rook_attacks(king_sq, them) & rooks_queens)) & them;
+
+        let mut pinned = BB::EMPTY;
+        for sniper in snipers {
+            let blockers = between(king_sq, sniper) & self.occupied_bb();
+            if blockers.count() == 1 {
+                pinned |= blockers & self.color_bb(color);
+            }
+        }
+        pinned
+    }
+}
+
+fn is_promotion_rank(rank: Rank) -> bool {
+    rank == Rank::First || rank == Rank::Eighth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legal_move_count(fen: &str) -> usize {
+        Board::from_fen(fen).unwrap().legal_moves().len()
+    }
+
+    fn contains(list: &MoveList, from: SQ, to: SQ, promotion: Option<PieceType>) -> bool {
+        list.iter().any(|m| m.origin_sq() == from && m.target_sq() == to && m.promotion() == promotion)
+    }
+
+    #[test]
+    fn start_position() {
+        let board = Board::from_fen(Board::START_FEN).unwrap();
+        let mut pseudo_legal = MoveList::new();
+        board.generate_moves(&mut pseudo_legal);
+        assert_eq!(pseudo_legal.len(), 20);
+        assert_eq!(board.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn standard_positions() {
+        assert_eq!(legal_move_count("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 48);
+        assert_eq!(legal_move_count("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 14);
+        assert_eq!(legal_move_count("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 6);
+        assert_eq!(legal_move_count("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"), 44);
+        assert_eq!(legal_move_count("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"), 46);
+    }
+
+    #[test]
+    fn promotions_to_all_four_pieces() {
+        let moves = Board::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap().legal_moves();
+        for promotion in PROMOTION_TYPES {
+            assert!(contains(&moves, SQ::A7, SQ::A8, Some(promotion)));
+            assert!(contains(&moves, SQ::A7, SQ::B8, Some(promotion)));
+        }
+        assert!(!contains(&moves, SQ::A7, SQ::A8, None));
+    }
+
+    #[test]
+    fn double_check_allows_only_king_moves() {
+        let moves = Board::from_fen("4k3/8/8/8/1b6/8/4r3/R3K2R w KQ - 0 1").unwrap().legal_moves();
+        assert!(moves.iter().all(|m| m.origin_sq() == SQ::E1));
+        assert!(!moves.is_empty());
+    }
+
+    #[test]
+    fn pinned_pieces_stay_on_the_pin_line() {
+        let moves = Board::from_fen("4k3/4r3/8/8/8/4R3/8/4K3 w - - 0 1").unwrap().legal_moves();
+        assert!(contains(&moves, SQ::E3, SQ::E7, None));
+        assert!(contains(&moves, SQ::E3, SQ::E2, None));
+        assert!(!contains(&moves, SQ::E3, SQ::D3, None));
+
+        let moves = Board::from_fen("4k3/8/8/b7/8/8/3N4/4K3 w - - 0 1").unwrap().legal_moves();
+        assert!(moves.iter().all(|m| m.origin_sq() != SQ::D2));
+    }
+
+    #[test]
+    fn en_passant_discovered_check_is_illegal() {
+        let moves = Board::from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").unwrap().legal_moves();
+        assert!(!contains(&moves, SQ::E5, SQ::D6, None));
+
+        let moves = Board::from_fen("8/8/8/K2pP3/8/8/8/7k w - d6 0 1").unwrap().legal_moves();
+        assert!(contains(&moves, SQ::E5, SQ::D6, None));
+    }
+
+    #[test]
+    fn en_passant_can_capture_a_checking_pawn() {
+        let moves = Board::from_fen("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").unwrap().legal_moves();
+        assert!(contains(&moves, SQ::E4, SQ::D3, None));
+    }
+
+    #[test]
+    fn castling_requires_empty_and_unattacked_path() {
+        let moves = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap().legal_moves();
+        assert!(contains(&moves, SQ::E1, SQ::H1, None));
+        assert!(contains(&moves, SQ::E1, SQ::A1, None));
+
+        // b1 may be attacked when castling queenside, d1 may not.
+        let moves = Board::from_fen("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap().legal_moves();
+        assert!(contains(&moves, SQ::E1, SQ::A1, None));
+        let moves = Board::from_fen("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap().legal_moves();
+        assert!(!contains(&moves, SQ::E1, SQ::A1, None));
+        assert!(contains(&moves, SQ::E1, SQ::H1, None));
+
+        let moves = Board::from_fen("4k3/8/8/8/8/8/8/RN2K1NR w KQ - 0 1").unwrap().legal_moves();
+        assert!(!contains(&moves, SQ::E1, SQ::H1, None));
+        assert!(!contains(&moves, SQ::E1, SQ::A1, None));
+
+        let moves = Board::from_fen("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1").unwrap().legal_moves();
+        assert!(!contains(&moves, SQ::E1, SQ::H1, None));
+        assert!(!contains(&moves, SQ::E1, SQ::A1, None));
+    }
+
+    #[test]
+    fn captures_are_the_capturing_legal_moves_plus_queen_promotions() {
+        let fens = [
+            Board::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let mut captures = MoveList::new();
+            board.generate_captures(&mut captures);
+
+            let expected: Vec<Move> = board.legal_moves()
+                .iter()
+                .copied()
+                .filter(|m| match m.promotion() {
+                    Some(promotion) => promotion == PieceType::Queen,
+                    None => !m.is_castle() && board.piece_on(m.target_sq()).is_some() || Some(m.target_sq()) == board.en_passant()
+                        && board.piece_on(m.origin_sq()).unwrap().type_of() == PieceType::Pawn
+                })
+                .collect();
+            assert_eq!(captures.len(), expected.len(), "{}", fen);
+            assert!(expected.iter().all(|m| captures.contains(m)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn captures_and_quiets_split_the_legal_moves() {
+        for fen in TRICKY_FENS {
+            let board = Board::from_fen(fen).unwrap();
+            let mut moves = MoveList::new();
+            board.generate_captures(&mut moves);
+            board.generate_quiets(&mut moves);
+
+            let legal = board.legal_moves();
+            assert_eq!(moves.len(), legal.len(), "{}", fen);
+            assert!(legal.iter().all(|m| moves.contains(m)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn is_legal_agrees_with_generation() {
+        // Every flag is tried, so a move is also rejected when its squares
+        // are legal but its flag does not fit them.
+        for fen in TRICKY_FENS {
+            let board = Board::from_fen(fen).unwrap();
+            let legal = board.legal_moves();
+            for from in SQ::VARIANTS {
+                for to in SQ::VARIANTS {
+                    for flag in MoveFlag::VARIANTS {
+                        let m = Move::new(from, to, flag);
+                        assert_eq!(board.is_legal(m), legal.contains(&m), "{:?} in {}", m, fen);
+                    }
+                }
+            }
+        }
+    }
+
+    const TRICKY_FENS: [&str; 9] = [
+        Board::START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+        "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        "4k3/8/8/8/1b6/8/4r3/R3K2R w KQ - 0 1",
+        "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1"
+    ];
+
+    #[test]
+    fn checkers_and_check() {
+        let board = Board::from_fen(Board::START_FEN).unwrap();
+        assert_eq!(board.checkers(), BB::EMPTY);
+        assert!(!board.in_check());
+
+        let board = Board::from_fen("4k3/8/5N2/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(board.checkers(), BB::from_sq(SQ::F6));
+        assert!(board.in_check());
+
+        let board = Board::from_fen("3k4/8/8/8/8/8/4R3/4K2b w - - 0 1").unwrap();
+        assert_eq!(board.checkers(), BB::EMPTY);
+        let board = Board::from_fen("4k3/8/8/8/8/8/4R3/4K2b b - - 0 1").unwrap();
+        assert_eq!(board.checkers(), BB::from_sq(SQ::E2));
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        let mate = Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(mate.is_checkmate());
+        assert!(!mate.is_stalemate());
+
+        let stalemate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_stalemate());
+        assert!(!stalemate.is_checkmate());
+
+        let check = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(!check.is_checkmate());
+        assert!(!check.is_stalemate());
+    }
+
+    #[test]
+    fn attackers_to_respects_occupancy() {
+        let board = Board::from_fen("4k3/8/8/3q4/8/1B6/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.attackers_to(SQ::A5, board.occupied_bb()), BB::from_sq(SQ::A1) | BB::from_sq(SQ::D5));
+        assert_eq!(board.attackers_to(SQ::A4, board.occupied_bb()), BB::from_sq(SQ::A1) | BB::from_sq(SQ::B3));
+
+        // Removing the bishop reveals the queen behind it on the diagonal.
+        let occupied = board.occupied_bb() & !BB::from_sq(SQ::B3);
+        assert!(board.attackers_to(SQ::A2, board.occupied_bb()).is_set(SQ::B3));
+        assert!(!board.attackers_to(SQ::A2, board.occupied_bb()).is_set(SQ::D5));
+        assert!(board.attackers_to(SQ::A2, occupied).is_set(SQ::D5));
+    }
+
+    #[test]
+    fn legal_moves_match_filtered_pseudo_legal_moves() {
+        let fens = [
+            Board::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+        ];
+
+        for fen in fens {
+            let mut board = Board::from_fen(fen).unwrap();
+            let mut pseudo_legal = MoveList::new();
+            board.generate_moves(&mut pseudo_legal);
+
+            let mut expected = Vec::new();
+            for m in pseudo_legal.iter().copied() {
+                let us = board.side_to_move();
+                let undo = board.make_move(m);
+                if !board.is_attacked(board.king_sq(us), !us, board.occupied_bb()) {
+                    expected.push(m);
+                }
+                board.unmake_move(m, undo);
+            }
+
+            let legal = board.legal_moves();
+            assert_eq!(legal.len(), expected.len(), "{}", fen);
+            assert!(expected.iter().all(|m| legal.contains(m)), "{}", fen);
+        }
+    }
+}
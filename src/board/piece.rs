@@ -1,10 +1,7 @@
-#[allow(dead_code)]
-
 use std::ops::{Index, IndexMut};
 use std::fmt::Display;
 
 use crate::board::color::Color;
-use crate::error::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
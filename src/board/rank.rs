@@ -1,10 +1,8 @@
-#[allow(dead_code)]
-
 use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 use std::fmt::Display;
 
-use crate::board::*;
+use crate::board::Color;
 
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
@@ -55,8 +53,25 @@ impl Rank {
         self as u8
     }
 
-    pub const fn to_string(self) -> String {
-        
+    pub const fn to_char(self) -> char {
+        match self {
+            Rank::First   => '1',
+            Rank::Second  => '2',
+            Rank::Third   => '3',
+            Rank::Fourth  => '4',
+            Rank::Fifth   => '5',
+            Rank::Sixth   => '6',
+            Rank::Seventh => '7',
+            Rank::Eighth  => '8'
+        }
+    }
+
+    /// The rank `color`'s pieces start on.
+    pub const fn back_rank(color: Color) -> Rank {
+        match color {
+            Color::White => Rank::First,
+            Color::Black => Rank::Eighth
+        }
     }
 
     pub const fn up(self) -> Option<Rank> {
@@ -124,17 +139,7 @@ impl FromStr for Rank {
 
 impl Display for Rank {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let string =match self {
-            Rank::First   => "1".to_string(),
-            Rank::Second  => "2".to_string(),
-            Rank::Third   => "3".to_string(),
-            Rank::Fourth  => "4".to_string(),
-            Rank::Fifth   => "5".to_string(),
-            Rank::Sixth   => "6".to_string(),
-            Rank::Seventh => "7".to_string(),
-            Rank::Eighth  => "8".to_string()
-        };
-        write!(f, "{}", string)
+        write!(f, "{}", self.to_char())
     }
 }
 
@@ -165,6 +170,7 @@ mod tests {
 
     #[test]
     #[cfg(debug_assertions)]
+    #[should_panic]
     fn from_u8_invalid() {
         let _ = Rank::from_u8(Rank::COUNT as u8);
     }
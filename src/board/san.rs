@@ -0,0 +1,316 @@
+use std::fmt::Display;
+use std::fmt::Write;
+
+use crate::board::{Board, Color, File, Move, MoveFlag, Piece, PieceType, Rank, SQ};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanError {
+    Empty,
+    InvalidSyntax,
+    InvalidPromotion(char),
+    IllegalMove,
+    AmbiguousMove
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::Empty               => write!(f, "empty move"),
+            SanError::InvalidSyntax       => write!(f, "invalid move syntax"),
+            SanError::InvalidPromotion(c) => write!(f, "invalid promotion piece '{}'", c),
+            SanError::IllegalMove         => write!(f, "no legal move matches"),
+            SanError::AmbiguousMove       => write!(f, "more than one legal move matches")
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+/// The uppercase letter used for `piece_type` in SAN.
+fn piece_letter(piece_type: PieceType) -> char {
+    Piece::new(Color::White, piece_type).to_char()
+}
+
+impl Board {
+    /// Formats the legal move `m` in Standard Algebraic Notation, with only as
+    /// much disambiguation as needed and a `+` or `#` suffix.
+    pub fn san(&self, m: Move) -> String {
+        let from = m.origin_sq();
+        let to = m.target_sq();
+        let piece = self.pieces[from].expect("san called without a piece on the origin square");
+        let mut san = String::new();
+
+        if m.is_castle() {
+            san.push_str(if m.flag() == MoveFlag::KingCastle { "O-O" } else { "O-O-O" });
+        } else if piece.type_of() == PieceType::Pawn {
+            if from.file() != to.file() {
+                write!(san, "{}x", from.file()).unwrap();
+            }
+            write!(san, "{}", to).unwrap();
+            if let Some(promotion) = m.promotion() {
+                write!(san, "={}", piece_letter(promotion)).unwrap();
+            }
+        } else {
+            san.push(piece_letter(piece.type_of()));
+
+            let rivals: Vec<SQ> = self.legal_moves()
+                .iter()
+                .filter(|other| other.target_sq() == to && other.origin_sq() != from)
+                .filter(|other| self.pieces[other.origin_sq()] == Some(piece))
+                .map(|other| other.origin_sq())
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|sq| sq.file() != from.file()) {
+                    write!(san, "{}", from.file()).unwrap();
+                } else if rivals.iter().all(|sq| sq.rank() != from.rank()) {
+                    write!(san, "{}", from.rank()).unwrap();
+                } else {
+                    write!(san, "{}", from).unwrap();
+                }
+            }
+
+            if self.pieces[to].is_some() {
+                san.push('x');
+            }
+            write!(san, "{}", to).unwrap();
+        }
+
+        let mut after = self.clone();
+        after.make_move(m);
+        if after.is_checkmate() {
+            san.push('#');
+        } else if after.in_check() {
+            san.push('+');
+        }
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation. Castling may be written
+    /// with letter O or digit 0, and check marks, annotations like `!?` and
+    /// an `e.p.` suffix are ignored. The capture `x` may be omitted, but if
+    /// given it must stand just before the target square of a capture. A
+    /// pawn capture always names the pawn's file, as in `exd5` or `ed5`.
+    pub fn parse_san(&self, s: &str) -> Result<Move, SanError> {
+        let mut s = s.trim();
+        for suffix in ["e.p.", "ep"] {
+            if let Some(stripped) = s.strip_suffix(suffix) {
+                s = stripped.trim_end();
+            }
+        }
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+        if s.is_empty() {
+            return Err(SanError::Empty);
+        }
+
+        match s {
+            "O-O" | "0-0" => return self.find_castle(MoveFlag::KingCastle),
+            "O-O-O" | "0-0-0" => return self.find_castle(MoveFlag::QueenCastle),
+            _ => {}
+        }
+
+        let mut chars: Vec<char> = s.chars().collect();
+
+        let piece_type = match chars.first() {
+            Some('N') => PieceType::Knight,
+            Some('B') => PieceType::Bishop,
+            Some('R') => PieceType::Rook,
+            Some('Q') => PieceType::Queen,
+            Some('K') => PieceType::King,
+            _ => PieceType::Pawn
+        };
+        if piece_type != PieceType::Pawn {
+            chars.remove(0);
+        }
+
+        let mut promotion = None;
+        if piece_type == PieceType::Pawn && let Some(&last) = chars.last() && !last.is_ascii_digit() {
+            promotion = Some(match last.to_ascii_uppercase() {
+                'N' => PieceType::Knight,
+                'B' => PieceType::Bishop,
+                'R' => PieceType::Rook,
+                'Q' => PieceType::Queen,
+                _ => return Err(SanError::InvalidPromotion(last))
+            });
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(SanError::InvalidSyntax);
+        }
+        let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to: SQ = target.parse().map_err(|_| SanError::InvalidSyntax)?;
+        let capture = chars.last() == Some(&'x');
+        if capture {
+            chars.pop();
+        }
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &chars {
+            if let Some(file) = File::from_char(c) && from_file.is_none() {
+                from_file = Some(file);
+            } else if let Some(rank) = Rank::from_char(c) && from_rank.is_none() {
+                from_rank = Some(rank);
+            } else {
+                return Err(SanError::InvalidSyntax);
+            }
+        }
+        if piece_type == PieceType::Pawn && capture && from_file.is_none() {
+            return Err(SanError::InvalidSyntax);
+        }
+
+        let mut found = None;
+        for m in self.legal_moves() {
+            let from = m.origin_sq();
+            let matches = m.target_sq() == to
+                && !m.is_castle()
+                && m.promotion() == promotion
+                && (m.is_capture() || !capture)
+                && (piece_type != PieceType::Pawn || from_file.is_some() || from.file() == to.file())
+                && self.pieces[from].map(Piece::type_of) == Some(piece_type)
+                && from_file.is_none_or(|file| file == from.file())
+                && from_rank.is_none_or(|rank| rank == from.rank());
+            if matches {
+                if found.is_some() {
+                    return Err(SanError::AmbiguousMove);
+                }
+                found = Some(m);
+            }
+        }
+        found.ok_or(SanError::IllegalMove)
+    }
+
+    fn find_castle(&self, flag: MoveFlag) -> Result<Move, SanError> {
+        self.legal_moves()
+            .iter()
+            .copied()
+            .find(|m| m.flag() == flag)
+            .ok_or(SanError::IllegalMove)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, uci: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        board.san(board.parse_uci_move(uci, false).unwrap())
+    }
+
+    #[test]
+    fn san_of_simple_moves() {
+        assert_eq!(san(Board::START_FEN, "e2e4"), "e4");
+        assert_eq!(san(Board::START_FEN, "g1f3"), "Nf3");
+        assert_eq!(san("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), "exd5");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+        assert_eq!(san("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1", "d1d5"), "Qxd5");
+    }
+
+    #[test]
+    fn san_of_castling_and_promotion() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "e1g1"), "O-O");
+        assert_eq!(san(fen, "e1c1"), "O-O-O");
+        assert_eq!(san("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), "e8=Q+");
+        assert_eq!(san("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8n"), "e8=N");
+        assert_eq!(san("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q"), "exd8=Q+");
+    }
+
+    #[test]
+    fn san_disambiguation() {
+        // Knights on b8 and f6 can both reach d7: the file tells them apart.
+        assert_eq!(san("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "b8d7"), "Nbd7");
+        // Rooks on a1 and a5 share a file: the rank tells them apart.
+        assert_eq!(san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        // Three queens where neither file nor rank alone is unique.
+        assert_eq!(san("2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1", "h4e1"), "Qh4e1");
+        // A pinned knight is not a rival.
+        assert_eq!(san("k3r3/8/8/8/8/8/4N3/1N2K3 w - - 0 1", "b1c3"), "Nc3");
+    }
+
+    #[test]
+    fn san_check_and_mate_suffixes() {
+        assert_eq!(san("r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 3", "h5f7"), "Qxf7#");
+        assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+    }
+
+    #[test]
+    fn parse_san_variants() {
+        let board = Board::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        assert_eq!(board.parse_san("O-O"), Ok(Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.parse_san("0-0-0"), Ok(Move::new(SQ::E1, SQ::A1, MoveFlag::QueenCastle)));
+        assert_eq!(board.parse_san("exd6 e.p."), Ok(Move::new(SQ::E5, SQ::D6, MoveFlag::EnPassant)));
+        assert_eq!(board.parse_san("ed6"), Ok(Move::new(SQ::E5, SQ::D6, MoveFlag::EnPassant)));
+        assert_eq!(board.parse_san("Rxa8+!?"), Ok(Move::new(SQ::A1, SQ::A8, MoveFlag::Capture)));
+        assert_eq!(board.parse_san("Ra8"), Ok(Move::new(SQ::A1, SQ::A8, MoveFlag::Capture)));
+        assert_eq!(board.parse_san("Rh1h7"), Ok(Move::new(SQ::H1, SQ::H7, MoveFlag::Quiet)));
+
+        let board = Board::from_fen("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("e8=Q+"), Ok(Move::new(SQ::E7, SQ::E8, MoveFlag::QueenPromotion)));
+        assert_eq!(board.parse_san("e8N"), Ok(Move::new(SQ::E7, SQ::E8, MoveFlag::KnightPromotion)));
+        assert_eq!(board.parse_san("e8=K"), Err(SanError::InvalidPromotion('K')));
+        assert_eq!(board.parse_san("e8"), Err(SanError::IllegalMove));
+    }
+
+    #[test]
+    fn chess960_king_moves_are_not_castles() {
+        let board = Board::from_fen("1r4k1/8/8/8/8/8/8/RR4K1 w Bb - 0 1").unwrap();
+        assert_eq!(board.parse_san("Kh1"), Ok(Move::new(SQ::G1, SQ::H1, MoveFlag::Quiet)));
+        assert_eq!(board.parse_san("O-O-O"), Ok(Move::new(SQ::G1, SQ::B1, MoveFlag::QueenCastle)));
+        assert_eq!(board.san(Move::new(SQ::G1, SQ::B1, MoveFlag::QueenCastle)), "O-O-O");
+
+        let board = Board::from_fen("1r4kr/8/8/8/8/8/8/RR4KR w HBhb - 0 1").unwrap();
+        assert_eq!(board.parse_san("O-O"), Ok(Move::new(SQ::G1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(board.parse_san("Rh1"), Err(SanError::IllegalMove));
+    }
+
+    #[test]
+    fn parse_san_errors() {
+        let board = Board::from_fen("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(board.parse_san("Nd7"), Err(SanError::AmbiguousMove));
+        assert_eq!(board.parse_san("Nbd7"), Ok(Move::new(SQ::B8, SQ::D7, MoveFlag::Quiet)));
+        assert_eq!(board.parse_san("N8d7"), Ok(Move::new(SQ::B8, SQ::D7, MoveFlag::Quiet)));
+        assert_eq!(board.parse_san("Nd5"), Ok(Move::new(SQ::F6, SQ::D5, MoveFlag::Quiet)));
+        assert_eq!(board.parse_san("Nd4"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("O-O"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san(""), Err(SanError::Empty));
+        assert_eq!(board.parse_san("N"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("Nz9"), Err(SanError::InvalidSyntax));
+    }
+
+    #[test]
+    fn parse_san_rejects_misplaced_capture_marks() {
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("exd5"), Ok(Move::new(SQ::E4, SQ::D5, MoveFlag::Capture)));
+        assert_eq!(board.parse_san("xd5"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("d5"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("Rax2"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("xRa2"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("Ra2x"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("Rxa2"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("e4-e5"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("Ra1-a2"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("e4:d5"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("e:d5"), Err(SanError::InvalidSyntax));
+    }
+
+    #[test]
+    fn every_legal_move_round_trips_through_san() {
+        let fens = [
+            Board::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.legal_moves() {
+                assert_eq!(board.parse_san(&board.san(m)), Ok(m), "{} {}", fen, m);
+            }
+        }
+    }
+}
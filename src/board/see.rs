@@ -0,0 +1,220 @@
+use crate::board::{BB, Board, Move, PieceType, Rank, SQ, bishop_attacks, rook_attacks};
+
+/// Piece values in centipawns, indexed by `PieceType`. The king is never
+/// traded, so it has no material value. These are the round values used
+/// for exchanges; the evaluation's own material is in `eval::psqt::MATERIAL`.
+pub const PIECE_VALUES: [i32; PieceType::COUNT] = [100, 320, 330, 500, 900, 0];
+
+/// What a pawn gains by promoting to a queen.
+const PROMOTION_GAIN: i32 = PIECE_VALUES[PieceType::Queen as usize] - PIECE_VALUES[PieceType::Pawn as usize];
+
+impl Board {
+    /// Static exchange evaluation: the material balance, from the mover's
+    /// point of view, of `m` followed by the best sequence of recaptures on
+    /// its target square. Each side captures with its least valuable
+    /// attacker and may stop whenever continuing would lose material.
+    /// Sliders behind a capturing piece join in once it has moved, and a
+    /// pawn recapturing onto its last rank becomes a queen.
+    pub fn see(&self, m: Move) -> i32 {
+        if m.is_castle() {
+            return 0;
+        }
+
+        let (first_gain, mut on_square, mut occupied) = self.exchange_start(m);
+        let to = m.target_sq();
+        let mut gain = [0; 32];
+        gain[0] = first_gain;
+
+        let mut attackers = self.attackers_to(to, occupied) & occupied;
+        let mut side = !self.side_to_move;
+        let mut depth = 0;
+        while let Some((sq, piece_type)) = self.least_valuable_attacker(attackers & self.color_bb(side)) {
+            // The king may only capture if nothing can take it back.
+            if piece_type == PieceType::King && attackers & self.color_bb(!side) != BB::EMPTY {
+                break;
+            }
+
+            let promotes = piece_type == PieceType::Pawn && to.rank() == Rank::back_rank(!side);
+            depth += 1;
+            gain[depth] = PIECE_VALUES[on_square] + if promotes { PROMOTION_GAIN } else { 0 } - gain[depth - 1];
+            on_square = if promotes { PieceType::Queen } else { piece_type };
+
+            occupied &= !BB::from_sq(sq);
+            attackers |= self.sliders_to(to, occupied);
+            attackers &= occupied;
+            side = !side;
+        }
+
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    /// Whether `see(m)` is at least `threshold`. Rather than playing out the
+    /// whole exchange, this stops as soon as one side can settle it: the
+    /// side to recapture gives up once even taking the piece for free
+    /// would not reach its goal, and its opponent stands pat once even
+    /// losing that piece would not drop below it.
+    pub fn see_ge(&self, m: Move, threshold: i32) -> bool {
+        if m.is_castle() {
+            return threshold <= 0;
+        }
+
+        // The mover's result against `threshold` if the exchange stops here.
+        let (first_gain, mut on_square, mut occupied) = self.exchange_start(m);
+        let mut balance = first_gain - threshold;
+        if balance < 0 {
+            return false;
+        }
+
+        let us = self.side_to_move;
+        let to = m.target_sq();
+        let mut attackers = self.attackers_to(to, occupied) & occupied;
+        let mut side = !us;
+        while let Some((sq, piece_type)) = self.least_valuable_attacker(attackers & self.color_bb(side)) {
+            if piece_type == PieceType::King && attackers & self.color_bb(!side) != BB::EMPTY {
+                break;
+            }
+
+            let promotes = piece_type == PieceType::Pawn && to.rank() == Rank::back_rank(!side);
+            let gain = PIECE_VALUES[on_square] + if promotes { PROMOTION_GAIN } else { 0 };
+            if side == us {
+                if balance + gain < 0 {
+                    return false;
+                }
+                balance += gain;
+            } else {
+                if balance - gain >= 0 {
+                    return true;
+                }
+                balance -= gain;
+            }
+            on_square = if promotes { PieceType::Queen } else { piece_type };
+
+            occupied &= !BB::from_sq(sq);
+            attackers |= self.sliders_to(to, occupied);
+            attackers &= occupied;
+            side = !side;
+        }
+        balance >= 0
+    }
+
+    /// The material `m` itself wins, the piece it leaves on the target
+    /// square and the occupancy once it is made, where an exchange on that
+    /// square starts from.
+    fn exchange_start(&self, m: Move) -> (i32, PieceType, BB) {
+        let from = m.origin_sq();
+        let to = m.target_sq();
+        let piece = self.pieces[from].expect("see called without a piece on the origin square");
+
+        let mut occupied = self.occupied_bb() & !BB::from_sq(from);
+        let mut gain = match self.pieces[to] {
+            Some(victim) => PIECE_VALUES[victim.type_of()],
+            None if m.is_en_passant() => {
+                let captured_sq = to.backward(piece.color()).expect("en passant target square is never on the back rank");
+                occupied &= !BB::from_sq(captured_sq);
+                PIECE_VALUES[PieceType::Pawn]
+            },
+            None => 0
+        };
+
+        let mut on_square = piece.type_of();
+        if let Some(promotion) = m.promotion() {
+            gain += PIECE_VALUES[promotion] - PIECE_VALUES[PieceType::Pawn];
+            on_square = promotion;
+        }
+        (gain, on_square, occupied)
+    }
+
+    fn least_valuable_attacker(&self, attackers: BB) -> Option<(SQ, PieceType)> {
+        PieceType::VARIANTS.into_iter().find_map(|piece_type| {
+            let sq = (attackers & self.piece_type_bbs[piece_type]).lsb_sq()?;
+            Some((sq, piece_type))
+        })
+    }
+
+    /// Bishops, rooks and queens of either color attacking `sq` through `occupied`.
+    fn sliders_to(&self, sq: SQ, occupied: BB) -> BB {
+        let queens = self.piece_type_bbs[PieceType::Queen];
+        (bishop_attacks(sq, occupied) & (self.piece_type_bbs[PieceType::Bishop] | queens))
+            | (rook_attacks(sq, occupied) & (self.piece_type_bbs[PieceType::Rook] | queens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, uci: &str) -> i32 {
+        let board = Board::from_fen(fen).unwrap();
+        board.see(board.parse_uci_move(uci, false).unwrap())
+    }
+
+    #[test]
+    fn simple_captures() {
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+        assert_eq!(see("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1d5"), -800);
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+    }
+
+    #[test]
+    fn x_ray_attackers_join_the_exchange() {
+        // Rxd5 cxd5 Qxd5: two pawns for a rook.
+        assert_eq!(see("4k3/8/2p5/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5"), -300);
+        // With a second defender white stops after cxd5.
+        assert_eq!(see("4k3/8/2p1p3/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5"), -400);
+    }
+
+    #[test]
+    fn king_cannot_recapture_a_defended_piece() {
+        assert_eq!(see("3k4/3q4/8/8/8/8/8/3RK3 w - - 0 1", "d1d7"), 400);
+        assert_eq!(see("3k4/3q4/8/8/8/8/3R4/3RK3 w - - 0 1", "d2d7"), 900);
+    }
+
+    #[test]
+    fn quiet_moves_and_promotions() {
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2N1K3 w - - 0 1", "c1b3"), 0);
+        assert_eq!(see("4k3/8/8/3p4/8/8/5N2/4K3 w - - 0 1", "f2e4"), -320);
+        assert_eq!(see("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), 800);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -100);
+    }
+
+    #[test]
+    fn recapturing_pawns_promote() {
+        // Qxb8 Rxb8 axb8=Q: the knight for nothing once the pawn promotes.
+        assert_eq!(see("rn2k3/P7/8/8/8/8/8/1Q2K3 w - - 0 1", "b1b8"), 320);
+        assert_eq!(see("rn2k3/8/8/8/8/8/8/1Q2K3 w - - 0 1", "b1b8"), -580);
+    }
+
+    #[test]
+    fn see_ge_thresholds() {
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/3R4/3QK3 w - - 0 1").unwrap();
+        let m = board.parse_uci_move("d2d5", false).unwrap();
+        assert!(board.see_ge(m, -300));
+        assert!(!board.see_ge(m, -299));
+        assert!(!board.see_ge(m, 0));
+    }
+
+    #[test]
+    fn see_ge_agrees_with_see() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "4k3/8/2p1p3/3p4/8/8/3R4/3QK3 w - - 0 1",
+            "rn2k3/P7/8/8/8/8/8/1Q2K3 w - - 0 1",
+            "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "3k4/3q4/8/8/8/8/3R4/3RK3 w - - 0 1"
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.legal_moves() {
+                let see = board.see(m);
+                for threshold in [-1000, -500, -100, -1, 0, 1, 100, 500, 1000, see, see + 1] {
+                    assert_eq!(board.see_ge(m, threshold), see >= threshold, "{} {} {}", fen, m, threshold);
+                }
+            }
+        }
+    }
+}
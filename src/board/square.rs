@@ -1,5 +1,3 @@
-#[allow(dead_code)]
-
 use std::fmt::Display;
 use std::str::FromStr;
 use std::ops::{Index, IndexMut};
@@ -23,7 +21,7 @@ pub enum SQ {
 impl SQ {
     pub const COUNT: usize = 64;
 
-    pub const VARIANTS: [SQ; SQ::COUNT as usize] = [
+    pub const VARIANTS: [SQ; SQ::COUNT] = [
         SQ::A1, SQ::B1, SQ::C1, SQ::D1, SQ::E1, SQ::F1, SQ::G1, SQ::H1,
         SQ::A2, SQ::B2, SQ::C2, SQ::D2, SQ::E2, SQ::F2, SQ::G2, SQ::H2,
         SQ::A3, SQ::B3, SQ::C3, SQ::D3, SQ::E3, SQ::F3, SQ::G3, SQ::H3,
@@ -34,7 +32,7 @@ impl SQ {
         SQ::A8, SQ::B8, SQ::C8, SQ::D8, SQ::E8, SQ::F8, SQ::G8, SQ::H8,
     ];
 
-    pub const STRINGS: [&'static str; SQ::COUNT as usize] = [
+    pub const STRINGS: [&'static str; SQ::COUNT] = [
         "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1",
         "a2", "b2", "c2", "d2", "e2", "f2", "g2", "h2",
         "a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3",
@@ -59,7 +57,7 @@ impl SQ {
     }
 
     pub const fn color(self) -> Color {
-        Color::from_u8(!(((self.to_u8() >> 3) ^ (self.to_u8() & 1))))
+        Color::from_u8(!((self.to_u8() >> 3) ^ (self.to_u8() & 1)))
     }
 
     pub const fn rank(self) -> Rank {
@@ -164,6 +162,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(debug_assertions)]
     #[should_panic]
     fn from_u8_invalid() {
         let _ = SQ::from_u8(SQ::COUNT as u8);
@@ -253,8 +252,8 @@ mod tests {
     #[test]
     fn offset() {
         assert_eq!(SQ::D4.offset(0, 0),   Some(SQ::D4));
-        assert_eq!(SQ::A1.offset(8, 8),   Some(SQ::H8));
-        assert_eq!(SQ::H8.offset(-8, -8), Some(SQ::A1));
+        assert_eq!(SQ::A1.offset(7, 7),   Some(SQ::H8));
+        assert_eq!(SQ::H8.offset(-7, -7), Some(SQ::A1));
 
         assert_eq!(SQ::A1.offset(-1, 0), None);
         assert_eq!(SQ::A1.offset(0, -1), None);
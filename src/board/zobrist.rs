@@ -0,0 +1,119 @@
+use crate::board::{CastleRights, Color, File, Piece, PieceType, SQ};
+
+pub fn piece_key(piece: Piece, sq: SQ) -> u64 {
+    PIECE_KEYS[piece.color()][piece.type_of()][sq]
+}
+
+/// The combined key for every castle right in `rights`.
+pub fn castle_key(rights: CastleRights) -> u64 {
+    CASTLE_KEYS[rights.to_u8() as usize]
+}
+
+pub fn en_passant_key(file: File) -> u64 {
+    EN_PASSANT_KEYS[file]
+}
+
+pub fn side_key() -> u64 {
+    SIDE_KEY
+}
+
+const SEED: u64 = 0x2F6B_3C1D_9A84_E507;
+
+/// One step of the splitmix64 generator, returning the new state and output.
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const KEY_COUNT: usize = Color::COUNT * PieceType::COUNT * SQ::COUNT
+    + Color::COUNT * 2
+    + File::COUNT
+    + 1;
+
+static KEYS: [u64; KEY_COUNT] = {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        let (next_state, key) = splitmix64(state);
+        keys[i] = key;
+        state = next_state;
+        i += 1;
+    }
+    keys
+};
+
+static PIECE_KEYS: [[[u64; SQ::COUNT]; PieceType::COUNT]; Color::COUNT] = {
+    let mut table = [[[0; SQ::COUNT]; PieceType::COUNT]; Color::COUNT];
+    let mut i = 0;
+    while i < Color::COUNT * PieceType::COUNT * SQ::COUNT {
+        table[i / (PieceType::COUNT * SQ::COUNT)][(i / SQ::COUNT) % PieceType::COUNT][i % SQ::COUNT] = KEYS[i];
+        i += 1;
+    }
+    table
+};
+
+const CASTLE_OFFSET: usize = Color::COUNT * PieceType::COUNT * SQ::COUNT;
+
+/// One key per set of castle rights, each the XOR of the keys of the
+/// rights it holds.
+static CASTLE_KEYS: [u64; 16] = {
+    let mut table = [0; 16];
+    let mut rights = 0;
+    while rights < 16 {
+        let mut bit = 0;
+        while bit < 4 {
+            if rights & (1 << bit) != 0 {
+                table[rights] ^= KEYS[CASTLE_OFFSET + bit];
+            }
+            bit += 1;
+        }
+        rights += 1;
+    }
+    table
+};
+
+const EN_PASSANT_OFFSET: usize = CASTLE_OFFSET + Color::COUNT * 2;
+
+static EN_PASSANT_KEYS: [u64; File::COUNT] = {
+    let mut table = [0; File::COUNT];
+    let mut i = 0;
+    while i < File::COUNT {
+        table[i] = KEYS[EN_PASSANT_OFFSET + i];
+        i += 1;
+    }
+    table
+};
+
+static SIDE_KEY: u64 = KEYS[EN_PASSANT_OFFSET + File::COUNT];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_distinct_and_non_zero() {
+        let mut keys = KEYS.to_vec();
+        assert!(keys.iter().all(|&key| key != 0));
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), KEY_COUNT);
+    }
+
+    #[test]
+    fn castle_key_combines_rights() {
+        let white = castle_key(CastleRights::both(Color::White));
+        let kingside = castle_key(CastleRights::WHITE_KINGSIDE);
+        let queenside = castle_key(CastleRights::WHITE_QUEENSIDE);
+        assert_eq!(white, kingside ^ queenside);
+        assert_eq!(castle_key(CastleRights::NONE), 0);
+        assert_ne!(kingside, castle_key(CastleRights::BLACK_KINGSIDE));
+        assert_eq!(
+            castle_key(CastleRights::ALL),
+            white ^ castle_key(CastleRights::BLACK_KINGSIDE) ^ castle_key(CastleRights::BLACK_QUEENSIDE)
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::board::{Board, Color, File, Move, PieceType, Rank, SQ};
+
+/// The number of values in Polyglot's Random64 table: 768 piece-square keys,
+/// 4 castle keys, 8 en passant keys and the side to move key.
+pub const RANDOM_COUNT: usize = 781;
+
+const CASTLE_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+const ENTRY_SIZE: usize = 16;
+
+// Polyglot's Random64 table, as published with the book format.
+include!("polyglot_random.rs");
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    InvalidBookLength(usize),
+    InvalidKeysLength(usize)
+}
+
+impl Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "{}", e),
+            BookError::InvalidBookLength(len) => {
+                write!(f, "book length {} is not a multiple of {} bytes", len, ENTRY_SIZE)
+            },
+            BookError::InvalidKeysLength(len) => {
+                write!(f, "key table length {} should be {} bytes", len, RANDOM_COUNT * 8)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<std::io::Error> for BookError {
+    fn from(e: std::io::Error) -> Self {
+        BookError::Io(e)
+    }
+}
+
+/// The random table used to compute the keys books are indexed by.
+///
+/// `PolyglotKeys::default()` is Polyglot's own Random64 table, which every
+/// Polyglot book is keyed with. Other tables can be read as 781 big-endian
+/// `u64` values in Polyglot's order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolyglotKeys {
+    random: Box<[u64; RANDOM_COUNT]>
+}
+
+impl Default for PolyglotKeys {
+    fn default() -> Self {
+        PolyglotKeys::from_values(RANDOM64)
+    }
+}
+
+impl PolyglotKeys {
+    pub fn from_values(random: [u64; RANDOM_COUNT]) -> Self {
+        PolyglotKeys { random: Box::new(random) }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if bytes.len() != RANDOM_COUNT * 8 {
+            return Err(BookError::InvalidKeysLength(bytes.len()));
+        }
+
+        let mut random = [0; RANDOM_COUNT];
+        for (value, chunk) in random.iter_mut().zip(bytes.chunks_exact(8)) {
+            *value = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Ok(PolyglotKeys::from_values(random))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        PolyglotKeys::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// The Polyglot key of `board`. As in Polyglot, the en passant file only
+    /// counts when a pawn of the side to move can capture onto the square.
+    pub fn key(&self, board: &Board) -> u64 {
+        let mut key = 0;
+        for sq in board.occupied_bb() {
+            if let Some(piece) = board.piece_on(sq) {
+                let kind = 2 * piece.type_of().to_u8() as usize + (piece.color() == Color::White) as usize;
+                key ^= self.random[64 * kind + sq.to_u8() as usize];
+            }
+        }
+
+        // Polyglot orders its castling keys as the rights' bits are ordered.
+        let rights = board.castle_rights().to_u8();
+        for bit in 0..4 {
+            if rights & (1 << bit) != 0 {
+                key ^= self.random[CASTLE_OFFSET + bit];
+            }
+        }
+
+        if let Some(ep) = board.capturable_en_passant() {
+            key ^= self.random[EN_PASSANT_OFFSET + ep.file().to_u8() as usize];
+        }
+
+        if board.side_to_move() == Color::White {
+            key ^= self.random[TURN_OFFSET];
+        }
+        key
+    }
+}
+
+/// One 16-byte record of a Polyglot book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    key: u64,
+    raw_move: u16,
+    weight: u16,
+    learn: u32
+}
+
+impl BookEntry {
+    pub fn new(key: u64, raw_move: u16, weight: u16, learn: u32) -> Self {
+        BookEntry { key, raw_move, weight, learn }
+    }
+
+    pub fn key(self) -> u64 {
+        self.key
+    }
+
+    pub fn raw_move(self) -> u16 {
+        self.raw_move
+    }
+
+    pub fn weight(self) -> u16 {
+        self.weight
+    }
+
+    pub fn learn(self) -> u32 {
+        self.learn
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap())
+        }
+    }
+
+    /// Decodes the entry's move for `board`. Polyglot writes castles as the
+    /// king taking its rook, which is how castles are encoded here too.
+    /// Returns `None` if the move is not legal in `board`.
+    pub fn decode_move(self, board: &Board) -> Option<Move> {
+        let square = |bits: u16| {
+            let file = File::from_u8((bits & 7) as u8);
+            let rank = Rank::from_u8(((bits >> 3) & 7) as u8);
+            SQ::from_coords(file, rank)
+        };
+        let from = square(self.raw_move >> 6);
+        let to = square(self.raw_move);
+        let promotion = match (self.raw_move >> 12) & 7 {
+            0 => None,
+            1 => Some(PieceType::Knight),
+            2 => Some(PieceType::Bishop),
+            3 => Some(PieceType::Rook),
+            4 => Some(PieceType::Queen),
+            _ => return None
+        };
+
+        let m = board.infer_move(from, to, promotion);
+        board.legal_moves().contains(&m).then_some(m)
+    }
+}
+
+/// A Polyglot opening book, with entries sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>
+}
+
+impl Book {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::InvalidBookLength(bytes.len()));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect();
+        if !entries.is_sorted_by_key(|entry| entry.key) {
+            entries.sort_by_key(|entry| entry.key);
+        }
+        Ok(Book { entries })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        Book::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries stored under `key`, found by binary search.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..end]
+    }
+
+    /// The legal book moves for `board` with their weights, skipping entries
+    /// whose move does not decode to a legal move.
+    pub fn moves(&self, board: &Board, keys: &PolyglotKeys) -> Vec<(Move, u16)> {
+        self.entries(keys.key(board))
+            .iter()
+            .filter_map(|entry| entry.decode_move(board).map(|m| (m, entry.weight)))
+            .collect()
+    }
+
+    /// Picks a book move for `board` with probability proportional to its
+    /// weight. Returns `None` if there is no move with a non-zero weight.
+    pub fn pick<R: Rng + ?Sized>(&self, board: &Board, keys: &PolyglotKeys, rng: &mut R) -> Option<Move> {
+        let moves = self.moves(board, keys);
+        let total: u32 = moves.iter().map(|&(_, weight)| weight as u32).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut choice = rng.random_range(0..total);
+        for (m, weight) in moves {
+            if choice < weight as u32 {
+                return Some(m);
+            }
+            choice -= weight as u32;
+        }
+        unreachable!("choice is below the total weight")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveFlag;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn raw_move(from: SQ, to: SQ, promotion: u16) -> u16 {
+        promotion << 12 | (from.to_u8() as u16) << 6 | to.to_u8() as u16
+    }
+
+    fn to_bytes(entries: &[BookEntry]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for entry in entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.raw_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// The Polyglot key after playing `moves` from the start position.
+    fn key_after(moves: &[&str]) -> u64 {
+        let mut board = Board::from_fen(Board::START_FEN).unwrap();
+        for s in moves {
+            let m = board.parse_uci_move(s, false).unwrap();
+            board.make_move(m);
+        }
+        PolyglotKeys::default().key(&board)
+    }
+
+    #[test]
+    fn known_keys() {
+        // The reference keys published with the Polyglot book format.
+        assert_eq!(key_after(&[]), 0x463b96181691fc9c);
+        assert_eq!(key_after(&["e2e4"]), 0x823c9b50fd114196);
+        assert_eq!(key_after(&["e2e4", "d7d5"]), 0x0756b94461c50fb0);
+        assert_eq!(key_after(&["e2e4", "d7d5", "e4e5"]), 0x662fafb965db29d4);
+        assert_eq!(key_after(&["e2e4", "d7d5", "e4e5", "f7f5"]), 0x22a48b5a8e47ff78);
+        assert_eq!(key_after(&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"]), 0x652a607ca3f242c1);
+        assert_eq!(key_after(&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"]), 0x00fdd303c946bdd9);
+        assert_eq!(key_after(&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"]), 0x3c8123ea7b067637);
+        assert_eq!(key_after(&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"]), 0x5c3f9b829b279560);
+    }
+
+    #[test]
+    fn key_counts_en_passant_only_when_capturable() {
+        let keys = PolyglotKeys::default();
+        let capturable = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let without = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 2").unwrap();
+        assert_eq!(keys.key(&capturable), keys.key(&without) ^ RANDOM64[EN_PASSANT_OFFSET + 3]);
+
+        let uncapturable = Board::from_fen("4k3/8/8/3p3P/8/8/8/4K3 w - d6 0 2").unwrap();
+        let without = Board::from_fen("4k3/8/8/3p3P/8/8/8/4K3 w - - 0 2").unwrap();
+        assert_eq!(keys.key(&uncapturable), keys.key(&without));
+    }
+
+    #[test]
+    fn keys_from_bytes() {
+        let keys = PolyglotKeys::default();
+        let bytes: Vec<u8> = RANDOM64.iter().flat_map(|value| value.to_be_bytes()).collect();
+        assert_eq!(PolyglotKeys::from_bytes(&bytes).unwrap(), keys);
+        assert!(matches!(PolyglotKeys::from_bytes(&bytes[1..]), Err(BookError::InvalidKeysLength(6247))));
+    }
+
+    #[test]
+    fn decode_castling_and_promotion() {
+        let board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let decode = |raw| BookEntry::new(0, raw, 1, 0).decode_move(&board);
+
+        assert_eq!(decode(raw_move(SQ::E1, SQ::H1, 0)), Some(Move::new(SQ::E1, SQ::H1, MoveFlag::KingCastle)));
+        assert_eq!(decode(raw_move(SQ::E1, SQ::A1, 0)), Some(Move::new(SQ::E1, SQ::A1, MoveFlag::QueenCastle)));
+        assert_eq!(decode(raw_move(SQ::B7, SQ::B8, 4)), Some(Move::new(SQ::B7, SQ::B8, MoveFlag::QueenPromotion)));
+        assert_eq!(decode(raw_move(SQ::B7, SQ::A8, 1)), Some(Move::new(SQ::B7, SQ::A8, MoveFlag::KnightPromotionCapture)));
+        assert_eq!(decode(raw_move(SQ::A1, SQ::A8, 0)), Some(Move::new(SQ::A1, SQ::A8, MoveFlag::Capture)));
+        assert_eq!(decode(raw_move(SQ::E1, SQ::E3, 0)), None);
+
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let entry = BookEntry::new(0, raw_move(SQ::E8, SQ::H8, 0), 1, 0);
+        assert_eq!(entry.decode_move(&board), Some(Move::new(SQ::E8, SQ::H8, MoveFlag::KingCastle)));
+    }
+
+    #[test]
+    fn lookup_and_weighted_pick() {
+        let keys = PolyglotKeys::default();
+        let board = Board::from_fen(Board::START_FEN).unwrap();
+        let key = keys.key(&board);
+
+        let e4 = raw_move(SQ::E2, SQ::E4, 0);
+        let d4 = raw_move(SQ::D2, SQ::D4, 0);
+        let a3 = raw_move(SQ::A2, SQ::A3, 0);
+        let bytes = to_bytes(&[
+            BookEntry::new(key.wrapping_sub(1), e4, 100, 0),
+            BookEntry::new(key, e4, 3, 0),
+            BookEntry::new(key, d4, 1, 0),
+            BookEntry::new(key, a3, 0, 0),
+            BookEntry::new(key.wrapping_add(1), d4, 100, 0),
+        ]);
+        let book = Book::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 5);
+        assert_eq!(book.entries(key).len(), 3);
+        assert!(book.entries(!key).is_empty());
+
+        let moves = book.moves(&board, &keys);
+        assert_eq!(moves, vec![
+            (Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush), 3),
+            (Move::new(SQ::D2, SQ::D4, MoveFlag::DoublePush), 1),
+            (Move::new(SQ::A2, SQ::A3, MoveFlag::Quiet), 0),
+        ]);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut e4_count = 0;
+        for _ in 0..1000 {
+            let m = book.pick(&board, &keys, &mut rng).unwrap();
+            assert_ne!(m, Move::new(SQ::A2, SQ::A3, MoveFlag::Quiet));
+            if m == Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush) {
+                e4_count += 1;
+            }
+        }
+        assert!((650..850).contains(&e4_count), "e4 picked {} times", e4_count);
+
+        let other = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(book.pick(&other, &keys, &mut rng), None);
+    }
+
+    #[test]
+    fn book_rejects_truncated_entries() {
+        assert!(matches!(Book::from_bytes(&[0; 17]), Err(BookError::InvalidBookLength(17))));
+        assert!(Book::from_bytes(&[]).unwrap().is_empty());
+    }
+}
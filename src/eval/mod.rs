@@ -0,0 +1,447 @@
+pub mod psqt;
+
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Sub};
+
+use crate::board::{
+    BB, Board, Color, File, Piece, PieceType, Rank, SQ,
+    bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks
+};
+
+/// The phase of the starting position. Each knight and bishop counts 1,
+/// each rook 2 and each queen 4; a bare board is a pure endgame.
+pub const MAX_PHASE: i32 = 24;
+const PHASE_WEIGHTS: [i32; PieceType::COUNT] = [0, 1, 1, 2, 4, 0];
+
+/// Bonus per attacked square beyond a typical count, for knights,
+/// bishops, rooks and queens. Squares held by friendly pieces or attacked
+/// by enemy pawns do not count.
+const MOBILITY: [Tapered; PieceType::COUNT] = [
+    Tapered::new(0, 0),
+    Tapered::new(4, 4),
+    Tapered::new(5, 5),
+    Tapered::new(2, 4),
+    Tapered::new(1, 2),
+    Tapered::new(0, 0)
+];
+const MOBILITY_BASELINE: [i32; PieceType::COUNT] = [0, 4, 7, 7, 14, 0];
+
+const DOUBLED_PAWN: Tapered = Tapered::new(-10, -20);
+const ISOLATED_PAWN: Tapered = Tapered::new(-10, -15);
+/// Bonus for a passed pawn by its rank counted from its own side.
+const PASSED_PAWN: [Tapered; Rank::COUNT] = [
+    Tapered::new(0, 0),
+    Tapered::new(5, 10),
+    Tapered::new(10, 15),
+    Tapered::new(15, 25),
+    Tapered::new(25, 45),
+    Tapered::new(40, 70),
+    Tapered::new(60, 110),
+    Tapered::new(0, 0)
+];
+
+/// Bonus per friendly pawn on the two ranks in front of the king.
+const PAWN_SHIELD: Tapered = Tapered::new(10, 0);
+/// Weight of an attack on the king's surroundings by piece type.
+const KING_ATTACK_WEIGHTS: [i32; PieceType::COUNT] = [0, 2, 2, 3, 5, 0];
+
+const BISHOP_PAIR: Tapered = Tapered::new(30, 50);
+
+/// A pair of middlegame and endgame scores, blended by the game phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tapered {
+    pub mg: i32,
+    pub eg: i32
+}
+
+impl Tapered {
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Tapered { mg, eg }
+    }
+
+    /// The score at `phase`, from `MAX_PHASE` for the opening down to 0
+    /// for a bare endgame.
+    pub fn blend(self, phase: i32) -> i32 {
+        let phase = phase.clamp(0, MAX_PHASE);
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Tapered {
+    type Output = Tapered;
+    fn add(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, rhs: Tapered) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Tapered;
+    fn sub(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl Neg for Tapered {
+    type Output = Tapered;
+    fn neg(self) -> Tapered {
+        Tapered::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Tapered {
+    type Output = Tapered;
+    fn mul(self, rhs: i32) -> Tapered {
+        Tapered::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+/// The parts the evaluation is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Term {
+    Material,
+    PieceSquares,
+    Mobility,
+    PawnStructure,
+    KingSafety,
+    BishopPair
+}
+
+impl Term {
+    pub const COUNT: usize = 6;
+    pub const VARIANTS: [Term; Term::COUNT] = [
+        Term::Material,
+        Term::PieceSquares,
+        Term::Mobility,
+        Term::PawnStructure,
+        Term::KingSafety,
+        Term::BishopPair
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Term::Material      => "Material",
+            Term::PieceSquares  => "Piece squares",
+            Term::Mobility      => "Mobility",
+            Term::PawnStructure => "Pawn structure",
+            Term::KingSafety    => "King safety",
+            Term::BishopPair    => "Bishop pair"
+        }
+    }
+}
+
+impl<T> Index<Term> for [T] {
+    type Output = T;
+
+    fn index(&self, term: Term) -> &Self::Output {
+        &self[term as usize]
+    }
+}
+
+impl<T> IndexMut<Term> for [T] {
+    fn index_mut(&mut self, term: Term) -> &mut Self::Output {
+        &mut self[term as usize]
+    }
+}
+
+/// Every term of an evaluation for each side, before blending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub terms: [[Tapered; Color::COUNT]; Term::COUNT],
+    pub phase: i32
+}
+
+impl Trace {
+    /// One term from White's point of view.
+    pub fn term(&self, term: Term) -> Tapered {
+        self.terms[term][Color::White] - self.terms[term][Color::Black]
+    }
+
+    /// The sum of all terms from White's point of view.
+    pub fn total(&self) -> Tapered {
+        Term::VARIANTS.into_iter().map(|term| self.term(term)).fold(Tapered::default(), Add::add)
+    }
+
+    /// The blended evaluation in centipawns from White's point of view.
+    pub fn score(&self) -> i32 {
+        self.total().blend(self.phase)
+    }
+}
+
+/// Formats the trace as a table of middlegame and endgame scores per term
+/// and side, followed by the blended total.
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<16}|     White     |     Black     |     Total", "Term")?;
+        writeln!(f, "{:<16}|    MG     EG  |    MG     EG  |    MG     EG", "")?;
+        writeln!(f, "{:-<16}+---------------+---------------+--------------", "")?;
+        for term in Term::VARIANTS {
+            let [white, black] = self.terms[term];
+            let total = self.term(term);
+            writeln!(
+                f,
+                "{:<16}| {:>5}  {:>5}  | {:>5}  {:>5}  | {:>5}  {:>5}",
+                term.name(), white.mg, white.eg, black.mg, black.eg, total.mg, total.eg
+            )?;
+        }
+        writeln!(f, "{:-<16}+---------------+---------------+--------------", "")?;
+        let total = self.total();
+        writeln!(f, "{:<16}|               |               | {:>5}  {:>5}", "Total", total.mg, total.eg)?;
+        write!(f, "Phase {}/{}, evaluation {} (White's point of view)", self.phase, MAX_PHASE, self.score())
+    }
+}
+
+/// Evaluates the position in centipawns from the side to move's point of view.
+pub fn evaluate(board: &Board) -> i32 {
+    let score = trace(board).score();
+    if board.side_to_move() == Color::White { score } else { -score }
+}
+
+/// Evaluates the position term by term, for seeing why the engine likes it.
+pub fn trace(board: &Board) -> Trace {
+    let mut trace = Trace {
+        terms: [[Tapered::default(); Color::COUNT]; Term::COUNT],
+        phase: 0
+    };
+
+    for color in Color::VARIANTS {
+        for piece_type in PieceType::VARIANTS {
+            let count = board.piece_count(Piece::new(color, piece_type)) as i32;
+            trace.terms[Term::Material][color] += psqt::MATERIAL[piece_type] * count;
+            trace.phase += PHASE_WEIGHTS[piece_type] * count;
+            for sq in board.piece_bb(Piece::new(color, piece_type)) {
+                trace.terms[Term::PieceSquares][color] += psqt::piece_square(color, piece_type, sq);
+            }
+        }
+
+        let (mobility, king_safety) = mobility_and_king_attacks(board, color);
+        trace.terms[Term::Mobility][color] = mobility;
+        trace.terms[Term::KingSafety][color] = king_safety + pawn_shield(board, color);
+        trace.terms[Term::PawnStructure][color] = pawn_structure(board, color);
+        if board.piece_count(Piece::new(color, PieceType::Bishop)) >= 2 {
+            trace.terms[Term::BishopPair][color] = BISHOP_PAIR;
+        }
+    }
+    trace.phase = trace.phase.min(MAX_PHASE);
+    trace
+}
+
+/// Mobility for `color`'s pieces, and the penalty `color`'s king takes from
+/// enemy pieces attacking the squares around it.
+fn mobility_and_king_attacks(board: &Board, color: Color) -> (Tapered, Tapered) {
+    let occupied = board.occupied_bb();
+    let mut mobility = Tapered::default();
+    for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        let area = !board.color_bb(color) & !pawn_attacks_of(board, !color);
+        for sq in board.piece_bb(Piece::new(color, piece_type)) {
+            let count = (piece_attacks(piece_type, sq, occupied) & area).count() as i32;
+            mobility += MOBILITY[piece_type] * (count - MOBILITY_BASELINE[piece_type]);
+        }
+    }
+
+    let Some(king_sq) = board.piece_bb(Piece::new(color, PieceType::King)).lsb_sq() else {
+        return (mobility, Tapered::default());
+    };
+    let king_zone = king_attacks(king_sq) | BB::from_sq(king_sq);
+    let mut attackers = 0;
+    let mut weight = 0;
+    for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        for sq in board.piece_bb(Piece::new(!color, piece_type)) {
+            let hits = (piece_attacks(piece_type, sq, occupied) & king_zone).count() as i32;
+            if hits > 0 {
+                attackers += 1;
+                weight += KING_ATTACK_WEIGHTS[piece_type] * hits;
+            }
+        }
+    }
+
+    // A lone attacker is rarely dangerous; several together grow quickly so.
+    let danger = if attackers >= 2 { weight * weight / 2 } else { 0 };
+    (mobility, Tapered::new(-danger.min(500), 0))
+}
+
+/// Bonus for `color`'s pawns standing just in front of its king.
+fn pawn_shield(board: &Board, color: Color) -> Tapered {
+    let Some(king_sq) = board.piece_bb(Piece::new(color, PieceType::King)).lsb_sq() else {
+        return Tapered::default();
+    };
+    let shield_ranks = [1, 2]
+        .into_iter()
+        .filter_map(|distance| king_sq.rank().offset(if color == Color::White { distance } else { -distance }))
+        .fold(BB::EMPTY, |bb, rank| bb | BB::from_rank(rank));
+    let shield = shield_ranks & (BB::from_file(king_sq.file()) | adjacent_files(king_sq.file()));
+    PAWN_SHIELD * (board.piece_bb(Piece::new(color, PieceType::Pawn)) & shield).count() as i32
+}
+
+/// Doubled, isolated and passed pawns for `color`.
+fn pawn_structure(board: &Board, color: Color) -> Tapered {
+    let ours = board.piece_bb(Piece::new(color, PieceType::Pawn));
+    let theirs = board.piece_bb(Piece::new(!color, PieceType::Pawn));
+    let mut score = Tapered::default();
+
+    for file in File::VARIANTS {
+        let on_file = (ours & BB::from_file(file)).count() as i32;
+        if on_file > 1 {
+            score += DOUBLED_PAWN * (on_file - 1);
+        }
+        if on_file > 0 && ours & adjacent_files(file) == BB::EMPTY {
+            score += ISOLATED_PAWN * on_file;
+        }
+    }
+
+    for sq in ours {
+        let lanes = BB::from_file(sq.file()) | adjacent_files(sq.file());
+        if theirs & lanes & ranks_ahead(sq.rank(), color) == BB::EMPTY {
+            let relative_rank = match color {
+                Color::White => sq.rank().to_u8(),
+                Color::Black => 7 - sq.rank().to_u8()
+            };
+            score += PASSED_PAWN[relative_rank as usize];
+        }
+    }
+    score
+}
+
+fn piece_attacks(piece_type: PieceType, sq: SQ, occupied: BB) -> BB {
+    match piece_type {
+        PieceType::Knight => knight_attacks(sq),
+        PieceType::Bishop => bishop_attacks(sq, occupied),
+        PieceType::Rook   => rook_attacks(sq, occupied),
+        PieceType::Queen  => queen_attacks(sq, occupied),
+        PieceType::Pawn | PieceType::King => unreachable!("only pieces have mobility")
+    }
+}
+
+fn pawn_attacks_of(board: &Board, color: Color) -> BB {
+    board
+        .piece_bb(Piece::new(color, PieceType::Pawn))
+        .fold(BB::EMPTY, |bb, sq| bb | pawn_attacks(sq, color))
+}
+
+fn adjacent_files(file: File) -> BB {
+    [file.left(), file.right()].into_iter().flatten().fold(BB::EMPTY, |bb, file| bb | BB::FILES[file as usize])
+}
+
+/// Every rank strictly in front of `rank` from `color`'s side.
+fn ranks_ahead(rank: Rank, color: Color) -> BB {
+    Rank::VARIANTS
+        .into_iter()
+        .filter(|&other| if color == Color::White { other > rank } else { other < rank })
+        .fold(BB::EMPTY, |bb, other| bb | BB::RANKS[other as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same position with the colors swapped and the board flipped.
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| -> String {
+            s.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect()
+        };
+        let placement = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>().join("/");
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        format!("{} {} {} - {} {}", placement, side, swap_case(fields[2]), fields[4], fields[5])
+    }
+
+    fn eval(fen: &str) -> i32 {
+        evaluate(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn start_position_is_balanced() {
+        let trace = trace(&Board::from_fen(Board::START_FEN).unwrap());
+        assert_eq!(trace.phase, MAX_PHASE);
+        for term in Term::VARIANTS {
+            assert_eq!(trace.term(term), Tapered::default(), "{:?}", term);
+        }
+        assert_eq!(trace.score(), 0);
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        for fen in [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "8/5pk1/6p1/3P4/1r6/6P1/5PK1/2R5 b - - 0 40",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "r3k2r/1pp2ppp/p1n5/4q3/8/2N2Q2/PPP2PPP/R3R1K1 w kq - 0 15"
+        ] {
+            assert_eq!(eval(fen), eval(&mirror(fen)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn phase_tapers_towards_the_endgame() {
+        assert_eq!(Tapered::new(100, 20).blend(MAX_PHASE), 100);
+        assert_eq!(Tapered::new(100, 20).blend(0), 20);
+        assert_eq!(Tapered::new(100, 20).blend(MAX_PHASE / 2), 60);
+
+        let trace = trace(&Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap());
+        assert_eq!(trace.phase, 2);
+    }
+
+    #[test]
+    fn pawn_structure_terms() {
+        let structure = |fen| trace(&Board::from_fen(fen).unwrap()).term(Term::PawnStructure);
+
+        // A lone pawn is isolated and, with nothing in front, passed.
+        assert_eq!(structure("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), ISOLATED_PAWN + PASSED_PAWN[1]);
+        // Doubled and isolated, with only the front pawn passed.
+        assert_eq!(
+            structure("4k3/8/8/8/4P3/8/4P3/4K3 w - - 0 1"),
+            DOUBLED_PAWN + ISOLATED_PAWN * 2 + PASSED_PAWN[1] + PASSED_PAWN[3]
+        );
+        // An enemy pawn on an adjacent file ahead stops the pawn being passed.
+        assert_eq!(structure("4k3/3p4/8/8/8/8/4P3/4K3 w - - 0 1"), Tapered::default());
+    }
+
+    #[test]
+    fn bishop_pair_and_king_shelter() {
+        let pair = trace(&Board::from_fen("4k3/8/8/8/8/8/5PPP/2B1KB2 w - - 0 1").unwrap());
+        assert_eq!(pair.term(Term::BishopPair), BISHOP_PAIR);
+
+        let sheltered = trace(&Board::from_fen("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap());
+        let exposed = trace(&Board::from_fen("4k3/8/8/8/8/5PPP/8/1K6 w - - 0 1").unwrap());
+        assert_eq!(sheltered.terms[Term::KingSafety][Color::White], PAWN_SHIELD * 3);
+        assert_eq!(exposed.terms[Term::KingSafety][Color::White], Tapered::default());
+    }
+
+    #[test]
+    fn attacks_on_the_king_are_penalised() {
+        let quiet = trace(&Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/Q5K1 w - - 0 1").unwrap());
+        let attacked = trace(&Board::from_fen("6k1/5ppp/7N/6Q1/8/8/5PPP/6K1 b - - 0 1").unwrap());
+        assert_eq!(quiet.terms[Term::KingSafety][Color::Black], PAWN_SHIELD * 3);
+        assert!(attacked.terms[Term::KingSafety][Color::Black].mg < 0);
+    }
+
+    #[test]
+    fn mobility_rewards_active_pieces() {
+        let mobility = |fen| trace(&Board::from_fen(fen).unwrap()).terms[Term::Mobility][Color::White];
+        assert!(mobility("4k3/8/8/3N4/8/8/8/4K3 w - - 0 1").mg > mobility("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").mg);
+    }
+
+    #[test]
+    fn trace_lists_every_term() {
+        let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let trace = trace(&board);
+        let text = trace.to_string();
+        for term in Term::VARIANTS {
+            assert!(text.contains(term.name()));
+        }
+        assert!(text.ends_with(&format!("evaluation {} (White's point of view)", trace.score())));
+        assert_eq!(evaluate(&board), trace.score());
+    }
+
+    #[test]
+    fn evaluation_is_from_side_to_move() {
+        assert!(eval("4k3/8/8/8/8/8/8/R3K3 w - - 0 1") > 400);
+        assert_eq!(eval("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), -eval("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+    }
+}
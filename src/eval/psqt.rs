@@ -0,0 +1,130 @@
+use crate::board::{Color, PieceType, SQ};
+use crate::eval::Tapered;
+
+/// Material values by piece type, tuned separately for the middlegame and
+/// the endgame.
+pub const MATERIAL: [Tapered; PieceType::COUNT] = [
+    Tapered::new(82, 94),
+    Tapered::new(337, 281),
+    Tapered::new(365, 297),
+    Tapered::new(477, 512),
+    Tapered::new(1025, 936),
+    Tapered::new(0, 0)
+];
+
+/// The piece-square bonus for `piece_type` of `color` standing on `sq`.
+pub fn piece_square(color: Color, piece_type: PieceType, sq: SQ) -> Tapered {
+    // The tables are written as seen from White with the eighth rank on
+    // top, so White's squares are flipped vertically and Black's are not.
+    let index = match color {
+        Color::White => sq.to_u8() ^ 56,
+        Color::Black => sq.to_u8()
+    } as usize;
+    Tapered::new(MIDGAME[piece_type][index], ENDGAME[piece_type][index])
+}
+
+const MIDGAME: [[i32; SQ::COUNT]; PieceType::COUNT] = [
+    PAWN_MIDGAME,
+    KNIGHT,
+    BISHOP,
+    ROOK,
+    QUEEN,
+    KING_MIDGAME
+];
+
+const ENDGAME: [[i32; SQ::COUNT]; PieceType::COUNT] = [
+    PAWN_ENDGAME,
+    KNIGHT,
+    BISHOP,
+    ROOK,
+    QUEEN,
+    KING_ENDGAME
+];
+
+const PAWN_MIDGAME: [i32; SQ::COUNT] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0
+];
+
+const PAWN_ENDGAME: [i32; SQ::COUNT] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     20,  20,  20,  20,  20,  20,  20,  20,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0
+];
+
+const KNIGHT: [i32; SQ::COUNT] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50
+];
+
+const BISHOP: [i32; SQ::COUNT] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20
+];
+
+const ROOK: [i32; SQ::COUNT] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0
+];
+
+const QUEEN: [i32; SQ::COUNT] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20
+];
+
+const KING_MIDGAME: [i32; SQ::COUNT] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20
+];
+
+const KING_ENDGAME: [i32; SQ::COUNT] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50
+];
@@ -0,0 +1,8 @@
+pub mod board;
+pub mod book;
+pub mod error;
+pub mod eval;
+pub mod perft;
+pub mod search;
+pub mod tt;
+pub mod uci;
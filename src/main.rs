@@ -1,11 +1,90 @@
-mod board;
-mod error;
+use std::process::ExitCode;
+use std::time::Instant;
 
-use crate::board::*;
+use chess::board::Board;
+use chess::perft::{divide, perft};
+use chess::uci::Uci;
 
-fn main() {
-    
-    //let mut magic_file = std::fs::File::create("magic_tables.rs").expect("Failed to create magic_tables.rs");
-    //write_magic(&mut magic_file).expect("Failed to write magic_tables.rs");
+const USAGE: &str = "usage: chess [uci]\n       chess perft <depth> [--fen <fen>] [--divide] [--chess960]";
+
+struct PerftArgs {
+    depth: u32,
+    fen: String,
+    divide: bool,
+    chess960: bool
+}
+
+fn parse_perft_args(args: &[String]) -> Result<PerftArgs, String> {
+    let mut depth = None;
+    let mut fen = Board::START_FEN.to_string();
+    let mut divide = false;
+    let mut chess960 = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--divide" => divide = true,
+            "--chess960" => chess960 = true,
+            "--fen" => {
+                fen = iter.next().ok_or("--fen requires a value")?.clone();
+            },
+            _ if depth.is_none() => {
+                depth = Some(arg.parse().map_err(|_| format!("invalid depth '{}'", arg))?);
+            },
+            _ => return Err(format!("unexpected argument '{}'", arg))
+        }
+    }
+
+    let depth = depth.ok_or("missing depth")?;
+    Ok(PerftArgs { depth, fen, divide, chess960 })
+}
+
+fn run_perft(args: PerftArgs) -> Result<(), String> {
+    let mut board = Board::from_fen(&args.fen).map_err(|e| format!("invalid fen: {}", e))?;
+    // Castles print as the king taking its rook when asked to, and whenever
+    // the king's two-step move could not say which rook castles.
+    let chess960 = args.chess960 || board.has_chess960_castling();
+
+    let start = Instant::now();
+    let nodes = if args.divide {
+        let counts = divide(&mut board, args.depth);
+        for (m, count) in &counts {
+            println!("{}: {}", m.to_uci(chess960), count);
+        }
+        println!();
+        counts.iter().map(|(_, count)| count).sum()
+    } else {
+        perft(&mut board, args.depth)
+    };
+    let elapsed = start.elapsed();
+
+    let nps = (nodes as f64 / elapsed.as_secs_f64().max(1e-9)) as u64;
+    println!("Nodes searched: {}", nodes);
+    println!("Time (ms): {}", elapsed.as_millis());
+    println!("Nodes/second: {}", nps);
+    Ok(())
 }
 
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        None | Some("uci") => {
+            Uci::new(std::io::stdout()).run(std::io::stdin().lock());
+            Ok(())
+        },
+        Some("perft") => parse_perft_args(&args[1..]).and_then(run_perft),
+        _ => Err(USAGE.to_string())
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            if message != USAGE {
+                eprintln!("{}", USAGE);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::board::{Board, Move};
+
+/// Counts the leaf nodes of the legal move tree `depth` plies deep.
+pub fn perft(board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = board.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for m in moves {
+        let undo = board.make_move(m);
+        nodes += perft(board, depth - 1);
+        board.unmake_move(m, undo);
+    }
+    nodes
+}
+
+/// Runs `perft` below each root move, returning the per-move counts in
+/// generation order.
+pub fn divide(board: &mut Board, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut counts = Vec::new();
+    for m in board.legal_moves() {
+        let undo = board.make_move(m);
+        counts.push((m, perft(board, depth - 1)));
+        board.unmake_move(m, undo);
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perft_start_position() {
+        let mut board = Board::from_fen(Board::START_FEN).unwrap();
+        assert_eq!(perft(&mut board, 0), 1);
+        assert_eq!(perft(&mut board, 1), 20);
+        assert_eq!(perft(&mut board, 2), 400);
+        assert_eq!(perft(&mut board, 3), 8902);
+        assert_eq!(board, Board::from_fen(Board::START_FEN).unwrap());
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let counts = divide(&mut board, 2);
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), perft(&mut board, 2));
+    }
+
+    /// Reference node counts by depth, from the Chess Programming Wiki perft
+    /// results and the commonly used edge-case collection.
+    const SUITE: [(&str, &[(u32, u64)]); 19] = [
+        (Board::START_FEN, &[
+            (1, 20), (2, 400), (3, 8_902), (4, 197_281), (5, 4_865_609), (6, 119_060_324)
+        ]),
+        // Kiwipete
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[
+            (1, 48), (2, 2_039), (3, 97_862), (4, 4_085_603), (5, 193_690_690)
+        ]),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[
+            (1, 14), (2, 191), (3, 2_812), (4, 43_238), (5, 674_624), (6, 11_030_083), (7, 178_633_661)
+        ]),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[
+            (1, 6), (2, 264), (3, 9_467), (4, 422_333), (5, 15_833_292)
+        ]),
+        ("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[
+            (1, 6), (2, 264), (3, 9_467), (4, 422_333), (5, 15_833_292)
+        ]),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[
+            (1, 44), (2, 1_486), (3, 62_379), (4, 2_103_487), (5, 89_941_194)
+        ]),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[
+            (1, 46), (2, 2_079), (3, 89_890), (4, 3_894_594), (5, 164_075_551)
+        ]),
+        // En passant would expose the king along the rank or diagonal
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", &[(6, 1_134_888)]),
+        ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", &[(6, 1_015_133)]),
+        // En passant capture gives check
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[(6, 1_440_467)]),
+        // Castling gives check
+        ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[(6, 661_072)]),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[(6, 803_711)]),
+        // Castle rights lost by captures, castling prevented by attacks
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[(4, 1_274_206)]),
+        ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", &[(4, 1_720_476)]),
+        // Promotion out of check, into check and underpromotion into check
+        ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[(6, 3_821_001)]),
+        ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[(6, 217_342)]),
+        ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[(6, 92_683)]),
+        // Discovered check, stalemate and checkmate
+        ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", &[(5, 1_004_658)]),
+        ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", &[(4, 23_527)]),
+    ];
+
+    /// Chess960 reference counts from the Chess Programming Wiki, with
+    /// castle rights in Shredder-FEN. The third position castles kingside
+    /// with the king and rook swapping squares.
+    const CHESS960_SUITE: [(&str, &[(u32, u64)]); 5] = [
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[
+            (1, 21), (2, 528), (3, 12_189), (4, 326_672), (5, 8_146_062)
+        ]),
+        ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[
+            (1, 21), (2, 807), (3, 18_002), (4, 667_366), (5, 16_253_601)
+        ]),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[
+            (1, 20), (2, 479), (3, 10_471), (4, 273_318), (5, 6_417_013)
+        ]),
+        ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", &[
+            (1, 22), (2, 593), (3, 13_440), (4, 382_958), (5, 9_183_776)
+        ]),
+        ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", &[
+            (1, 28), (2, 1_120), (3, 31_058), (4, 1_171_749), (5, 34_030_312)
+        ]),
+    ];
+
+    /// Reference node counts for Chess960 start positions by Scharnagl
+    /// index. Position 518 is the standard start position.
+    const CHESS960_START_POSITIONS: [(u16, &[(u32, u64)]); 5] = [
+        (0, &[(3, 9_006), (4, 201_143), (5, 4_975_808)]),
+        (1, &[(3, 8_948), (4, 198_393), (5, 4_863_733)]),
+        (2, &[(3, 8_988), (4, 200_096), (5, 4_916_733)]),
+        (518, &[(3, 8_902), (4, 197_281), (5, 4_865_609)]),
+        (959, &[(3, 9_006), (4, 201_143), (5, 4_973_573)]),
+    ];
+
+    fn run_suite(max_nodes: u64) {
+        let fens = SUITE.into_iter().chain(CHESS960_SUITE).map(|(fen, counts)| (Board::from_fen(fen).unwrap(), counts));
+        let start_positions = CHESS960_START_POSITIONS
+            .into_iter()
+            .map(|(index, counts)| (Board::chess960(index).unwrap(), counts));
+
+        for (mut board, counts) in fens.chain(start_positions) {
+            for &(depth, expected) in counts {
+                if expected <= max_nodes {
+                    assert_eq!(perft(&mut board, depth), expected, "{} at depth {}", board.to_shredder_fen(), depth);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn legal_moves_round_trip_through_their_squares() {
+        let round_trip = |board: &Board| {
+            for m in board.legal_moves() {
+                let (from, to, promotion) = m.to_parts();
+                assert_eq!(board.move_from_parts(from, to, promotion), Some(m), "{}", board.to_fen());
+                assert_eq!(board.infer_move(from, to, promotion), m, "{}", board.to_fen());
+            }
+        };
+
+        for (fen, _) in SUITE.into_iter().chain(CHESS960_SUITE) {
+            let mut board = Board::from_fen(fen).unwrap();
+            round_trip(&board);
+            for m in board.legal_moves() {
+                let undo = board.make_move(m);
+                round_trip(&board);
+                board.unmake_move(m, undo);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_suite_shallow() {
+        run_suite(250_000);
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_suite_deep() {
+        run_suite(u64::MAX);
+    }
+}
@@ -0,0 +1,1096 @@
+mod move_picker;
+mod smp;
+pub mod time;
+
+use std::fmt::Display;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::board::{Board, GameState, Move, MoveList, PIECE_VALUES, Piece, PieceType, SQ};
+use crate::eval::evaluate;
+use crate::tt::{Bound, TranspositionTable, TtEntry};
+use move_picker::{History, MovePicker, is_quiet};
+use smp::NodeCounter;
+pub use time::{TimeControl, TimeManager};
+use time::WallClock;
+
+/// The deepest ply the search will reach, which bounds the PV table.
+pub const MAX_PLY: usize = 128;
+
+/// The stack size for search threads, which recurse up to `MAX_PLY` deep
+/// with move lists in every frame.
+pub const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Larger than any score the search can return.
+pub const INFINITY: i32 = 32_000;
+
+/// The score of delivering mate at the root. Mate `n` plies away scores
+/// `MATE - n`, so shorter mates score higher.
+pub const MATE: i32 = 31_000;
+
+/// Scores at least this large in magnitude are mate scores.
+const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+const ASPIRATION_DEPTH: u32 = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+
+/// Slack added to a capture's material gain before delta pruning gives up on it.
+const DELTA_MARGIN: i32 = 200;
+
+/// How many nodes pass between checks of the clock and the stop flag.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Reverse futility pruning: at this depth or less a node whose static
+/// evaluation beats beta by this much per ply is not searched.
+const REVERSE_FUTILITY_DEPTH: i32 = 6;
+const REVERSE_FUTILITY_MARGIN: i32 = 80;
+
+/// Null-move pruning starts at this depth, and the null move is searched
+/// this much shallower, plus a ply for every four of depth.
+const NULL_MOVE_DEPTH: i32 = 3;
+const NULL_MOVE_REDUCTION: i32 = 3;
+
+/// Futility pruning: at this depth or less quiet moves are skipped when
+/// the static evaluation trails alpha by this much per ply.
+const FUTILITY_DEPTH: i32 = 3;
+const FUTILITY_MARGIN: i32 = 100;
+
+/// Late move pruning: at this depth or less only the first
+/// `LATE_MOVE_BASE + depth * depth` moves may be quiet.
+const LATE_MOVE_DEPTH: i32 = 4;
+const LATE_MOVE_BASE: i32 = 3;
+
+/// Late move reductions start at this depth, after the first move.
+const REDUCTION_DEPTH: i32 = 3;
+
+/// The reduction for the `moves`th move at `depth`, growing with the
+/// logarithm of both.
+static REDUCTIONS: LazyLock<[[i32; 64]; 64]> = LazyLock::new(|| {
+    let mut table = [[0; 64]; 64];
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (moves, reduction) in row.iter_mut().enumerate().skip(1) {
+            *reduction = (0.75 + (depth as f64).ln() * (moves as f64).ln() / 2.25) as i32;
+        }
+    }
+    table
+});
+
+/// A search score as reported to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns from the side to move's point of view.
+    Cp(i32),
+    /// Moves until mate, negative when the side to move is being mated.
+    Mate(i32)
+}
+
+impl Score {
+    pub fn from_internal(score: i32) -> Score {
+        if score >= MATE_BOUND {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score <= -MATE_BOUND {
+            Score::Mate(-(MATE + score) / 2)
+        } else {
+            Score::Cp(score)
+        }
+    }
+}
+
+/// Formats the score the way UCI `info` lines expect, e.g. `cp 35` or `mate -2`.
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp)      => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves)
+        }
+    }
+}
+
+/// Switches for the selective parts of the search, all on by default, so
+/// each can be measured on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchFeatures {
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility: bool,
+    pub futility: bool,
+    pub late_move_pruning: bool,
+    pub check_extensions: bool
+}
+
+impl SearchFeatures {
+    pub const NONE: SearchFeatures = SearchFeatures {
+        null_move: false,
+        late_move_reductions: false,
+        reverse_futility: false,
+        futility: false,
+        late_move_pruning: false,
+        check_extensions: false
+    };
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        SearchFeatures {
+            null_move: true,
+            late_move_reductions: true,
+            reverse_futility: true,
+            futility: true,
+            late_move_pruning: true,
+            check_extensions: true
+        }
+    }
+}
+
+/// When to stop searching. With no limits the search runs until stopped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// The clock of the side to move, when playing a timed game.
+    pub time_control: Option<TimeControl>,
+    /// Time kept back from every move for communication delays.
+    pub move_overhead: Duration,
+    /// How many threads search together; zero counts as one.
+    pub threads: usize,
+    pub features: SearchFeatures
+}
+
+/// Progress reported after each completed iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub seldepth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub nps: u64,
+    /// Permille of the transposition table in use.
+    pub hashfull: u32,
+    pub time: Duration,
+    pub pv: Vec<Move>
+}
+
+impl SearchInfo {
+    /// The info as the body of a UCI `info` line, with castles in the PV
+    /// written as the king taking its rook if `chess960` is set.
+    pub fn to_uci(&self, chess960: bool) -> String {
+        let mut line = format!(
+            "depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {} pv",
+            self.depth, self.seldepth, self.score, self.nodes, self.nps, self.hashfull, self.time.as_millis()
+        );
+        for m in &self.pv {
+            line.push(' ');
+            line.push_str(&m.to_uci(chess960));
+        }
+        line
+    }
+}
+
+/// Formats the info as the body of a UCI `info` line.
+impl Display for SearchInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_uci(false))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    pub score: Score,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Move>
+}
+
+/// The deepest iteration a thread completed.
+#[derive(Debug, Clone)]
+struct Iteration {
+    depth: u32,
+    seldepth: u32,
+    score: i32,
+    best_move: Option<Move>,
+    pv: Vec<Move>
+}
+
+impl Iteration {
+    fn info(&self, nodes: u64, time: Duration, hashfull: u32) -> SearchInfo {
+        SearchInfo {
+            depth: self.depth,
+            seldepth: self.seldepth,
+            score: Score::from_internal(self.score),
+            nodes,
+            nps: (nodes as f64 / time.as_secs_f64().max(1e-9)) as u64,
+            hashfull,
+            time,
+            pv: self.pv.clone()
+        }
+    }
+}
+
+/// Searches `state` with iterative deepening until a limit is reached or
+/// `stop` is set, calling `on_info` after each completed iteration.
+///
+/// With more than one thread the search is Lazy SMP: helper threads run
+/// the same search on their own, staggered over neighbouring depths, and
+/// share what they learn only through `tt`. The calling thread keeps the
+/// time and reports progress, stopping the helpers when it finishes, and
+/// the threads then vote on the move to play.
+pub fn search(
+    state: &GameState,
+    limits: &SearchLimits,
+    tt: &TranspositionTable,
+    stop: &AtomicBool,
+    mut on_info: impl FnMut(&SearchInfo)
+) -> SearchResult {
+    tt.new_search();
+    let board = state.board();
+    let root_moves = board.legal_moves();
+    if root_moves.is_empty() {
+        return SearchResult {
+            best_move: None,
+            score: Score::from_internal(if board.in_check() { -MATE } else { 0 }),
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new()
+        };
+    }
+
+    let threads = limits.threads.max(1);
+    let node_counts: Vec<NodeCounter> = (0..threads).map(|_| NodeCounter::default()).collect();
+    let helpers_stop = AtomicBool::new(false);
+    // Helpers leave the clock to the main thread. The node limit counts
+    // every thread's nodes, so each of them checks it.
+    let helper_limits = SearchLimits {
+        depth: limits.depth,
+        nodes: limits.nodes,
+        features: limits.features,
+        ..SearchLimits::default()
+    };
+
+    let (iterations, time) = std::thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads)
+            .map(|id| {
+                let (helper_limits, helpers_stop, node_counts) = (&helper_limits, &helpers_stop, &node_counts[..]);
+                std::thread::Builder::new()
+                    .name(format!("search helper {}", id))
+                    .stack_size(STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        Searcher::new(state, helper_limits, tt, helpers_stop, node_counts, id).iterate(|_| {})
+                    })
+                    .expect("failed to spawn a search thread")
+            })
+            .collect();
+
+        let mut main = Searcher::new(state, limits, tt, stop, &node_counts, 0);
+        let mut iterations = vec![main.iterate(&mut on_info)];
+        helpers_stop.store(true, Ordering::Relaxed);
+        iterations.extend(helpers.into_iter().map(|helper| helper.join().expect("search thread panicked")));
+        (iterations, main.time.elapsed())
+    });
+
+    let nodes = node_counts.iter().map(NodeCounter::get).sum();
+    let main_finished = iterations[0].is_some();
+    let finished: Vec<Iteration> = iterations.into_iter().flatten().collect();
+    let Some(best) = smp::vote(&finished) else {
+        return SearchResult {
+            best_move: root_moves.first().copied(),
+            score: Score::Cp(0),
+            depth: 0,
+            nodes,
+            pv: Vec::new()
+        };
+    };
+
+    // The last info line should show the line that will be played.
+    if !(main_finished && std::ptr::eq(best, &finished[0])) {
+        on_info(&best.info(nodes, time, tt.hashfull()));
+    }
+
+    SearchResult {
+        best_move: best.best_move,
+        score: Score::from_internal(best.score),
+        depth: best.depth,
+        nodes,
+        pv: best.pv.clone()
+    }
+}
+
+struct Searcher<'a> {
+    board: Board,
+    /// Hashes of the positions before the current one, for repetition checks.
+    hashes: Vec<u64>,
+    limits: &'a SearchLimits,
+    tt: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    time: TimeManager,
+    stopped: bool,
+    /// Which thread this is, 0 being the main thread.
+    id: usize,
+    nodes: u64,
+    /// Where every thread publishes its node count.
+    node_counts: &'a [NodeCounter],
+    /// The other threads' nodes as of the last check.
+    other_nodes: u64,
+    seldepth: usize,
+    pv_table: Box<[[Move; MAX_PLY]; MAX_PLY]>,
+    pv_len: [usize; MAX_PLY],
+    history: Box<History>,
+    /// The piece moved at each ply and where it went, for countermoves.
+    played: [Option<(Piece, SQ)>; MAX_PLY]
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        state: &GameState,
+        limits: &'a SearchLimits,
+        tt: &'a TranspositionTable,
+        stop: &'a AtomicBool,
+        node_counts: &'a [NodeCounter],
+        id: usize
+    ) -> Self {
+        let mut history = state.clone();
+        let mut hashes = Vec::new();
+        while history.unmake_move().is_some() {
+            hashes.push(history.board().hash());
+        }
+        hashes.reverse();
+
+        Searcher {
+            board: state.board().clone(),
+            hashes,
+            limits,
+            tt,
+            stop,
+            time: TimeManager::new(limits, state.board().legal_moves().len(), Box::new(WallClock::start())),
+            stopped: false,
+            id,
+            nodes: 0,
+            node_counts,
+            other_nodes: 0,
+            seldepth: 0,
+            pv_table: Box::new([[Move::NULL; MAX_PLY]; MAX_PLY]),
+            pv_len: [0; MAX_PLY],
+            history: History::new(),
+            played: [None; MAX_PLY]
+        }
+    }
+
+    /// Deepens the search one iteration at a time until a limit is reached,
+    /// calling `on_info` after each, and returns the deepest it completed.
+    fn iterate(&mut self, mut on_info: impl FnMut(&SearchInfo)) -> Option<Iteration> {
+        let max_depth = self.limits.depth.unwrap_or(MAX_PLY as u32 - 1).min(MAX_PLY as u32 - 1);
+        let mut finished: Option<Iteration> = None;
+        let mut score = 0;
+        for depth in 1..=max_depth {
+            if smp::skips_depth(self.id, depth) {
+                continue;
+            }
+
+            self.seldepth = 0;
+            let next = self.aspiration(depth, score);
+            if self.stopped {
+                break;
+            }
+            score = next;
+
+            let pv = self.pv();
+            let iteration = Iteration {
+                depth,
+                seldepth: self.seldepth as u32,
+                score,
+                best_move: pv.first().copied().or(finished.as_ref().and_then(|previous| previous.best_move)),
+                pv
+            };
+            if self.id == 0 {
+                self.publish_nodes();
+                let nodes = self.node_counts.iter().map(NodeCounter::get).sum();
+                on_info(&iteration.info(nodes, self.time.elapsed(), self.tt.hashfull()));
+            }
+            let best_move = iteration.best_move;
+            finished = Some(iteration);
+
+            // A mate found within this depth cannot be improved on by searching deeper.
+            if score.abs() >= MATE_BOUND && MATE - score.abs() <= depth as i32 {
+                break;
+            }
+
+            self.time.on_iteration(best_move, score);
+            if self.time.should_stop_iterating() {
+                break;
+            }
+        }
+
+        self.publish_nodes();
+        finished
+    }
+
+    /// Searches the root with a narrow window around the previous score,
+    /// widening it whenever the result falls outside.
+    fn aspiration(&mut self, depth: u32, previous: i32) -> i32 {
+        if depth < ASPIRATION_DEPTH {
+            return self.negamax(depth as i32, 0, -INFINITY, INFINITY);
+        }
+
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = (previous - delta).max(-INFINITY);
+        let mut beta = (previous + delta).min(INFINITY);
+        loop {
+            let score = self.negamax(depth as i32, 0, alpha, beta);
+            if self.stopped {
+                return score;
+            }
+
+            if score <= alpha {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+            delta *= 2;
+        }
+    }
+
+    fn negamax(&mut self, mut depth: i32, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        let features = self.limits.features;
+        let in_check = self.board.in_check();
+        if features.check_extensions && in_check {
+            depth += 1;
+        }
+
+        if depth <= 0 {
+            return self.quiescence(ply, alpha, beta);
+        }
+
+        self.pv_len[ply] = ply;
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply > 0 && self.is_draw() {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(&self.board);
+        }
+
+        let pv_node = beta - alpha > 1;
+        let key = self.board.hash();
+        let tt_entry = self.tt.probe(key);
+        if ply > 0
+            && let Some(entry) = tt_entry
+            && entry.depth as i32 >= depth {
+            let score = score_from_tt(entry.score, ply);
+            // An exact hit would cut the PV short, so only bounds end the
+            // search of a node whose window is still open.
+            let cutoff = match entry.bound {
+                Bound::Exact => !pv_node,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha
+            };
+            if cutoff {
+                return score;
+            }
+        }
+
+        let eval = if in_check { -INFINITY } else { evaluate(&self.board) };
+        let us = self.board.side_to_move();
+
+        // Reverse futility pruning: so far above beta that a shallow search
+        // is not going to bring the score back down.
+        if features.reverse_futility
+            && !pv_node
+            && !in_check
+            && depth <= REVERSE_FUTILITY_DEPTH
+            && beta.abs() < MATE_BOUND
+            && eval - REVERSE_FUTILITY_MARGIN * depth >= beta {
+            return eval;
+        }
+
+        // Null-move pruning: if passing the turn still fails high, a real
+        // move almost surely would. Two passes in a row would prove
+        // nothing, and with only pawns left zugzwang makes passing the
+        // better move too often to trust.
+        let after_null_move = ply > 0 && self.played[ply - 1].is_none();
+        if features.null_move
+            && !pv_node
+            && !in_check
+            && !after_null_move
+            && depth >= NULL_MOVE_DEPTH
+            && eval >= beta
+            && self.board.has_non_pawn_material(us) {
+            let reduction = NULL_MOVE_REDUCTION + depth / 4;
+            self.played[ply] = None;
+            self.hashes.push(key);
+            let undo = self.board.make_null_move();
+            let score = -self.negamax(depth - 1 - reduction, ply + 1, -beta, -beta + 1);
+            self.board.unmake_null_move(undo);
+            self.hashes.pop();
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                // A mate found after passing is not a proven mate.
+                return if score >= MATE_BOUND { beta } else { score };
+            }
+        }
+
+        let futile = features.futility
+            && !pv_node
+            && !in_check
+            && depth <= FUTILITY_DEPTH
+            && eval + FUTILITY_MARGIN * depth <= alpha;
+        let late_move_limit = if features.late_move_pruning && !pv_node && !in_check && depth <= LATE_MOVE_DEPTH {
+            LATE_MOVE_BASE + depth * depth
+        } else {
+            i32::MAX
+        };
+
+        let previous = ply.checked_sub(1).and_then(|prev| self.played[prev]);
+        let mut picker = MovePicker::new(
+            &self.board,
+            tt_entry.and_then(|entry| entry.best_move),
+            self.history.killers(ply),
+            self.history.countermove(previous)
+        );
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut quiets_tried = MoveList::new();
+        let mut move_count = 0;
+        while let Some(m) = picker.next(&self.board, &self.history) {
+            let quiet = is_quiet(m);
+            // Quiet moves are only pruned once a move has been searched and
+            // found not to lose, so a mate is never missed for want of them.
+            let may_prune = quiet && best > -MATE_BOUND;
+            if may_prune && move_count >= late_move_limit {
+                continue;
+            }
+
+            self.played[ply] = self.board.piece_on(m.origin_sq()).map(|piece| (piece, m.target_sq()));
+            self.hashes.push(key);
+            let undo = self.board.make_move(m);
+            let gives_check = self.board.in_check();
+            if may_prune && futile && !gives_check {
+                self.board.unmake_move(m, undo);
+                self.hashes.pop();
+                continue;
+            }
+
+            move_count += 1;
+            if quiet {
+                quiets_tried.push(m);
+            }
+            self.tt.prefetch(self.board.hash());
+
+            // The first move is searched with the full window. The rest
+            // only need to prove they are no better, with a null window
+            // and late quiet moves at reduced depth, and are searched
+            // again in full if they turn out better after all.
+            let new_depth = depth - 1;
+            let score = if move_count == 1 {
+                -self.negamax(new_depth, ply + 1, -beta, -alpha)
+            } else {
+                let reduction = if features.late_move_reductions
+                    && depth >= REDUCTION_DEPTH
+                    && quiet
+                    && !in_check
+                    && !gives_check {
+                    (reduction(depth, move_count) - pv_node as i32).clamp(0, new_depth - 1)
+                } else {
+                    0
+                };
+
+                let mut score = -self.negamax(new_depth - reduction, ply + 1, -alpha - 1, -alpha);
+                if score > alpha && reduction > 0 {
+                    score = -self.negamax(new_depth, ply + 1, -alpha - 1, -alpha);
+                }
+                if score > alpha && score < beta {
+                    score = -self.negamax(new_depth, ply + 1, -beta, -alpha);
+                }
+                score
+            };
+            self.board.unmake_move(m, undo);
+            self.hashes.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(m);
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        if quiet {
+                            self.history.update_quiet(us, ply, previous, m, &quiets_tried, depth);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        if move_count == 0 {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, TtEntry {
+            best_move,
+            score: score_to_tt(best, ply),
+            depth: depth as u8,
+            bound
+        });
+        best
+    }
+
+    /// Searches captures and queen promotions until the position is quiet,
+    /// so the static evaluation is never taken in the middle of an exchange.
+    /// When in check every evasion is searched instead.
+    fn quiescence(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_len[ply] = ply;
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if self.is_draw() {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(&self.board);
+        }
+
+        let in_check = self.board.in_check();
+        let stand_pat = if in_check {
+            -INFINITY
+        } else {
+            let stand_pat = evaluate(&self.board);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            stand_pat
+        };
+
+        let mut picker = MovePicker::quiescence(&self.board);
+        let mut best = stand_pat;
+        let mut move_count = 0;
+        while let Some(m) = picker.next(&self.board, &self.history) {
+            move_count += 1;
+
+            // Delta pruning: skip captures that could not raise alpha even
+            // if the captured material came for free.
+            if !in_check && stand_pat + self.material_gain(m) + DELTA_MARGIN <= alpha {
+                continue;
+            }
+
+            self.hashes.push(self.board.hash());
+            let undo = self.board.make_move(m);
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.board.unmake_move(m, undo);
+            self.hashes.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if in_check && move_count == 0 {
+            return -MATE + ply as i32;
+        }
+        best
+    }
+
+    /// The material `m` wins outright: the captured piece and, for a
+    /// promotion, the promoted piece in place of the pawn.
+    fn material_gain(&self, m: Move) -> i32 {
+        let captured = match self.board.piece_on(m.target_sq()) {
+            Some(victim) if m.is_capture() => PIECE_VALUES[victim.type_of()],
+            None if m.is_en_passant() => PIECE_VALUES[PieceType::Pawn],
+            _ => 0
+        };
+        let promoted = m.promotion().map_or(0, |promotion| PIECE_VALUES[promotion] - PIECE_VALUES[PieceType::Pawn]);
+        captured + promoted
+    }
+
+    /// Makes `m` followed by the PV of the child node the PV at `ply`.
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        self.pv_table[ply][ply] = m;
+        for i in ply + 1..self.pv_len[ply + 1] {
+            self.pv_table[ply][i] = self.pv_table[ply + 1][i];
+        }
+        self.pv_len[ply] = self.pv_len[ply + 1].max(ply + 1);
+    }
+
+    fn pv(&self) -> Vec<Move> {
+        self.pv_table[0][..self.pv_len[0]].to_vec()
+    }
+
+    /// Draws by the fifty-move rule or by repetition. A single repetition
+    /// inside the search is scored as a draw, since the side that could
+    /// avoid it would already have done so. Mate on the hundredth halfmove
+    /// still wins.
+    fn is_draw(&self) -> bool {
+        let halfmove_clock = self.board.halfmove_clock() as usize;
+        if halfmove_clock >= 100 {
+            return !self.board.is_checkmate();
+        }
+
+        let hash = self.board.hash();
+        self.hashes
+            .iter()
+            .rev()
+            .take(halfmove_clock)
+            .skip(1)
+            .step_by(2)
+            .any(|&previous| previous == hash)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        let check = self.nodes.is_multiple_of(CHECK_INTERVAL);
+        if check {
+            self.publish_nodes();
+            self.other_nodes = self.node_counts.iter().map(NodeCounter::get).sum::<u64>() - self.nodes;
+        }
+
+        // The node limit counts every thread's nodes.
+        if self.limits.nodes.is_some_and(|nodes| self.nodes + self.other_nodes >= nodes) {
+            self.stopped = true;
+        } else if check {
+            self.stopped = self.time.out_of_time() || self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+
+    fn publish_nodes(&self) {
+        self.node_counts[self.id].set(self.nodes);
+    }
+}
+
+/// How much less deep to search the `moves`th move at `depth`.
+fn reduction(depth: i32, moves: i32) -> i32 {
+    REDUCTIONS[depth.min(63) as usize][moves.min(63) as usize]
+}
+
+/// Mate scores count plies from the root, but an entry may be found at
+/// any ply, so the table stores them counted from the entry's own node.
+fn score_to_tt(score: i32, ply: usize) -> i16 {
+    let score = if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    };
+    score as i16
+}
+
+fn score_from_tt(score: i16, ply: usize) -> i32 {
+    let score = score as i32;
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveFlag;
+
+    fn search_fen(fen: &str, depth: u32) -> SearchResult {
+        let state = GameState::new(Board::from_fen(fen).unwrap());
+        let limits = SearchLimits { depth: Some(depth), ..SearchLimits::default() };
+        search(&state, &limits, &TranspositionTable::new(1), &AtomicBool::new(false), |_| {})
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(result.best_move, Some(Move::new(SQ::A1, SQ::A8, MoveFlag::Quiet)));
+        assert_eq!(result.score, Score::Mate(1));
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        // 1. Re8+ Rxe8 2. Rxe8#
+        let result = search_fen("3r2k1/5ppp/8/8/8/8/4RPPP/4R1K1 w - - 0 1", 4);
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn reports_being_mated() {
+        let result = search_fen("7k/8/8/8/8/8/5PPP/r5K1 w - - 0 1", 2);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::Mate(0));
+
+        let result = search_fen("8/8/8/8/8/6q1/P4k2/7K w - - 0 1", 3);
+        assert_eq!(result.score, Score::Mate(-1));
+    }
+
+    #[test]
+    fn wins_material() {
+        let result = search_fen("7k/6pp/8/3q4/8/8/3R4/3RK3 w - - 0 1", 3);
+        assert_eq!(result.best_move, Some(Move::new(SQ::D2, SQ::D5, MoveFlag::Capture)));
+        assert!(matches!(result.score, Score::Cp(cp) if cp > 400));
+    }
+
+    #[test]
+    fn quiescence_sees_recaptures_beyond_the_horizon() {
+        // At depth 1 Qxd5 looks like a free pawn, but cxd5 wins the queen.
+        let result = search_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", 1);
+        assert_ne!(result.best_move, Some(Move::new(SQ::D1, SQ::D5, MoveFlag::Capture)));
+        assert!(matches!(result.score, Score::Cp(cp) if (600..=800).contains(&cp)));
+
+        // An undefended piece is still taken.
+        let result = search_fen("4k3/8/8/3n4/8/8/8/3QK3 w - - 0 1", 1);
+        assert_eq!(result.best_move, Some(Move::new(SQ::D1, SQ::D5, MoveFlag::Capture)));
+    }
+
+    #[test]
+    fn quiescence_searches_evasions_when_in_check() {
+        let state = GameState::new(Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/r5K1 w - - 0 1").unwrap());
+        let limits = SearchLimits::default();
+        let tt = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+        let counts = [NodeCounter::default()];
+        let mut searcher = Searcher::new(&state, &limits, &tt, &stop, &counts, 0);
+        assert_eq!(searcher.quiescence(1, -INFINITY, INFINITY), -MATE + 1);
+
+        let state = GameState::new(Board::from_fen("6k1/5ppp/8/8/8/8/5PP1/r5K1 w - - 0 1").unwrap());
+        let mut searcher = Searcher::new(&state, &limits, &tt, &stop, &counts, 0);
+        assert!(searcher.quiescence(1, -INFINITY, INFINITY) > -MATE_BOUND);
+    }
+
+    #[test]
+    fn promotes() {
+        let result = search_fen("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1", 2);
+        assert_eq!(result.best_move, Some(Move::new(SQ::E7, SQ::E8, MoveFlag::QueenPromotion)));
+    }
+
+    #[test]
+    fn stalemate_scores_zero() {
+        let result = search_fen("7k/8/6Q1/8/8/8/8/K7 b - - 0 1", 3);
+        assert_eq!(result.score, Score::Cp(0));
+    }
+
+    #[test]
+    fn mate_on_the_hundredth_halfmove_is_not_a_draw() {
+        let result = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80", 3);
+        assert_eq!(result.best_move, Some(Move::new(SQ::A1, SQ::A8, MoveFlag::Quiet)));
+        assert_eq!(result.score, Score::Mate(1));
+
+        let limits = SearchLimits::default();
+        let tt = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+        let counts = [NodeCounter::default()];
+        let mated = GameState::new(Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80").unwrap());
+        assert!(!Searcher::new(&mated, &limits, &tt, &stop, &counts, 0).is_draw());
+        let drawn = GameState::new(Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 100 80").unwrap());
+        assert!(Searcher::new(&drawn, &limits, &tt, &stop, &counts, 0).is_draw());
+    }
+
+    #[test]
+    fn repetition_is_a_draw() {
+        let mut state = GameState::default();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            let m = state.board().parse_uci_move(uci, false).unwrap();
+            state.make_move(m);
+        }
+        let limits = SearchLimits::default();
+        let tt = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+        let counts = [NodeCounter::default()];
+        assert!(Searcher::new(&state, &limits, &tt, &stop, &counts, 0).is_draw());
+
+        state.unmake_move();
+        assert!(!Searcher::new(&state, &limits, &tt, &stop, &counts, 0).is_draw());
+    }
+
+    #[test]
+    fn reports_each_iteration() {
+        let state = GameState::default();
+        let limits = SearchLimits { depth: Some(4), ..SearchLimits::default() };
+        let mut infos = Vec::new();
+        let result = search(&state, &limits, &TranspositionTable::new(1), &AtomicBool::new(false), |info| infos.push(info.clone()));
+
+        assert_eq!(infos.iter().map(|info| info.depth).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert!(infos.iter().all(|info| !info.pv.is_empty() && info.seldepth >= info.depth));
+        assert_eq!(result.pv, infos[3].pv);
+        assert!(infos[3].to_string().starts_with(&format!("depth 4 seldepth {} score ", infos[3].seldepth)));
+    }
+
+    #[test]
+    fn respects_node_limit_and_stop_flag() {
+        let state = GameState::default();
+        let limits = SearchLimits { nodes: Some(5000), ..SearchLimits::default() };
+        let result = search(&state, &limits, &TranspositionTable::new(1), &AtomicBool::new(false), |_| {});
+        assert!(result.nodes <= 5000);
+        assert!(result.best_move.is_some());
+
+        let result = search(&state, &SearchLimits::default(), &TranspositionTable::new(1), &AtomicBool::new(true), |_| {});
+        assert_eq!(result.depth, 0);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn search_fills_the_transposition_table() {
+        let state = GameState::default();
+        let tt = TranspositionTable::new(1);
+        // Pruning lets a warm table change the move, so compare plain searches.
+        let limits = SearchLimits { depth: Some(4), features: SearchFeatures::NONE, ..SearchLimits::default() };
+        let result = search(&state, &limits, &tt, &AtomicBool::new(false), |_| {});
+
+        let entry = tt.probe(state.board().hash()).unwrap();
+        assert_eq!(entry.best_move, result.best_move);
+        assert_eq!(entry.depth, 4);
+        assert!(tt.hashfull() > 0);
+
+        // A second search reuses the table and agrees with the first.
+        let again = search(&state, &limits, &tt, &AtomicBool::new(false), |_| {});
+        assert_eq!(again.best_move, result.best_move);
+        assert!(again.nodes < result.nodes);
+    }
+
+    #[test]
+    fn threads_share_the_work() {
+        let state = GameState::new(Board::from_fen("3r2k1/5ppp/8/8/8/8/4RPPP/4R1K1 w - - 0 1").unwrap());
+        let limits = SearchLimits { depth: Some(5), threads: 4, ..SearchLimits::default() };
+        let tt = TranspositionTable::new(1);
+        let mut infos = Vec::new();
+        let result = search(&state, &limits, &tt, &AtomicBool::new(false), |info| infos.push(info.clone()));
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.best_move, Some(Move::new(SQ::E2, SQ::E8, MoveFlag::Quiet)));
+
+        // Info lines count every thread's nodes, so they never go down.
+        assert!(infos.windows(2).all(|pair| pair[0].nodes <= pair[1].nodes));
+        assert!(result.nodes >= infos.last().unwrap().nodes);
+
+        let limits = SearchLimits { nodes: Some(20_000), threads: 4, ..SearchLimits::default() };
+        let result = search(&GameState::default(), &limits, &tt, &AtomicBool::new(false), |_| {});
+        assert!(result.best_move.is_some());
+        // Each thread sees the others' counts only as of its last check.
+        assert!(result.nodes <= 20_000 + 4 * CHECK_INTERVAL);
+    }
+
+    fn search_with(fen: &str, depth: u32, features: SearchFeatures) -> SearchResult {
+        let state = GameState::new(Board::from_fen(fen).unwrap());
+        let limits = SearchLimits { depth: Some(depth), features, ..SearchLimits::default() };
+        search(&state, &limits, &TranspositionTable::new(1), &AtomicBool::new(false), |_| {})
+    }
+
+    #[test]
+    fn pruning_shrinks_the_tree() {
+        let all = search_with(Board::START_FEN, 6, SearchFeatures::default());
+        let none = search_with(Board::START_FEN, 6, SearchFeatures::NONE);
+        assert!(all.nodes * 2 < none.nodes, "{} nodes against {}", all.nodes, none.nodes);
+    }
+
+    #[test]
+    fn tactics_survive_each_feature_alone() {
+        let switches: [fn(&mut SearchFeatures) -> &mut bool; 6] = [
+            |features| &mut features.null_move,
+            |features| &mut features.late_move_reductions,
+            |features| &mut features.reverse_futility,
+            |features| &mut features.futility,
+            |features| &mut features.late_move_pruning,
+            |features| &mut features.check_extensions
+        ];
+        for switch in switches {
+            let mut alone = SearchFeatures::NONE;
+            *switch(&mut alone) = true;
+            let mut without = SearchFeatures::default();
+            *switch(&mut without) = false;
+
+            for features in [alone, without] {
+                let result = search_with("3r2k1/5ppp/8/8/8/8/4RPPP/4R1K1 w - - 0 1", 4, features);
+                assert_eq!(result.score, Score::Mate(2), "{:?}", features);
+                let result = search_with("7k/6pp/8/3q4/8/8/3R4/3RK3 w - - 0 1", 4, features);
+                assert_eq!(result.best_move, Some(Move::new(SQ::D2, SQ::D5, MoveFlag::Capture)), "{:?}", features);
+            }
+        }
+    }
+
+    #[test]
+    fn late_reductions_grow_with_depth_and_move_count() {
+        assert_eq!(reduction(3, 1), 0);
+        assert!(reduction(3, 20) >= 1);
+        assert!(reduction(20, 40) > reduction(6, 40));
+        assert!(reduction(10, 40) > reduction(10, 4));
+        assert_eq!(reduction(200, 300), reduction(63, 63));
+    }
+
+    #[test]
+    fn mate_scores_are_stored_relative_to_the_node() {
+        // Mate in 3 plies from a node at ply 2 is mate in 5 from the root.
+        let stored = score_to_tt(MATE - 5, 2);
+        assert_eq!(stored as i32, MATE - 3);
+        assert_eq!(score_from_tt(stored, 4), MATE - 7);
+        assert_eq!(score_from_tt(score_to_tt(-MATE + 6, 6), 1), -MATE + 1);
+        assert_eq!(score_from_tt(score_to_tt(-150, 9), 3), -150);
+    }
+
+    #[test]
+    fn single_legal_move_on_the_clock_is_played_at_once() {
+        // The cornered king can only take the rook on g1.
+        let state = GameState::new(Board::from_fen("4k3/8/8/8/8/8/r7/6rK w - - 0 1").unwrap());
+        let limits = SearchLimits {
+            time_control: Some(TimeControl { time: Duration::from_secs(60), ..TimeControl::default() }),
+            ..SearchLimits::default()
+        };
+        let result = search(&state, &limits, &TranspositionTable::new(1), &AtomicBool::new(false), |_| {});
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move, Some(Move::new(SQ::H1, SQ::G1, MoveFlag::Capture)));
+    }
+
+    #[test]
+    fn mate_score_conversion() {
+        assert_eq!(Score::from_internal(MATE - 1), Score::Mate(1));
+        assert_eq!(Score::from_internal(MATE - 3), Score::Mate(2));
+        assert_eq!(Score::from_internal(-MATE + 2), Score::Mate(-1));
+        assert_eq!(Score::from_internal(-MATE), Score::Mate(0));
+        assert_eq!(Score::from_internal(150), Score::Cp(150));
+        assert_eq!(Score::Mate(-3).to_string(), "mate -3");
+    }
+}
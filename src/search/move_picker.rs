@@ -0,0 +1,400 @@
+use crate::board::{Board, Color, MoveList, Move, PIECE_VALUES, Piece, PieceType, SQ};
+use crate::search::MAX_PLY;
+
+/// History scores are kept within this bound, so recent results outweigh
+/// old ones.
+const MAX_HISTORY: i32 = 16_384;
+
+/// Evasions that capture are tried before the quiet ones.
+const EVASION_CAPTURE_BONUS: i32 = 1 << 20;
+
+/// What the search has learned about quiet moves so far: moves that caused
+/// a cutoff at the same ply, replies that refuted the previous move, and a
+/// history score per side and move.
+pub struct History {
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    countermoves: [[[Option<Move>; SQ::COUNT]; PieceType::COUNT]; Color::COUNT],
+    butterfly: [[[i32; SQ::COUNT]; SQ::COUNT]; Color::COUNT]
+}
+
+impl History {
+    pub fn new() -> Box<History> {
+        Box::new(History {
+            killers: [[None; 2]; MAX_PLY],
+            countermoves: [[[None; SQ::COUNT]; PieceType::COUNT]; Color::COUNT],
+            butterfly: [[[0; SQ::COUNT]; SQ::COUNT]; Color::COUNT]
+        })
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers[ply]
+    }
+
+    /// The move that last refuted `previous`, given as the piece that moved
+    /// and the square it moved to.
+    pub fn countermove(&self, previous: Option<(Piece, SQ)>) -> Option<Move> {
+        let (piece, to) = previous?;
+        self.countermoves[piece.color()][piece.type_of()][to]
+    }
+
+    pub fn butterfly(&self, color: Color, m: Move) -> i32 {
+        self.butterfly[color][m.origin_sq()][m.target_sq()]
+    }
+
+    /// Records that the quiet move `best` caused a cutoff at `ply` after the
+    /// quiet moves in `tried` failed to, with `color` to move.
+    pub fn update_quiet(
+        &mut self,
+        color: Color,
+        ply: usize,
+        previous: Option<(Piece, SQ)>,
+        best: Move,
+        tried: &[Move],
+        depth: i32
+    ) {
+        if self.killers[ply][0] != Some(best) {
+            self.killers[ply] = [Some(best), self.killers[ply][0]];
+        }
+        if let Some((piece, to)) = previous {
+            self.countermoves[piece.color()][piece.type_of()][to] = Some(best);
+        }
+
+        let bonus = (depth * depth).min(MAX_HISTORY);
+        self.add_butterfly(color, best, bonus);
+        for &m in tried.iter().filter(|&&m| m != best) {
+            self.add_butterfly(color, m, -bonus);
+        }
+    }
+
+    fn add_butterfly(&mut self, color: Color, m: Move, bonus: i32) {
+        // Scaling by how full the entry already is keeps it within bounds
+        // and lets it swing back quickly when a move stops working.
+        let entry = &mut self.butterfly[color][m.origin_sq()][m.target_sq()];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+}
+
+/// Whether `m` belongs to the quiet moves, as `Board::generate_quiets`
+/// divides them: moves to empty squares other than queen promotions, and
+/// underpromotions.
+pub fn is_quiet(m: Move) -> bool {
+    match m.promotion() {
+        Some(promotion) => promotion != PieceType::Queen,
+        None => !m.is_capture()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    FirstKiller,
+    SecondKiller,
+    Countermove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateEvasions,
+    Evasions,
+    GenerateQuiescence,
+    QuiescenceCaptures,
+    Done
+}
+
+/// Hands out the legal moves of a position one at a time, best guesses
+/// first, generating each group only once the previous one is used up so
+/// a cutoff skips the work of the rest:
+///
+/// 1. the transposition table's move,
+/// 2. captures that do not lose material, by most valuable victim and
+///    least valuable attacker,
+/// 3. the two killer moves and the countermove,
+/// 4. the remaining quiet moves by history,
+/// 5. captures that lose material.
+///
+/// Moves and their scores live in a single `MoveList`. Losing captures are
+/// set aside at its front as the good ones are used, and the quiet moves
+/// are generated after the captures.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    countermove: Option<Move>,
+    moves: MoveList,
+    index: usize,
+    bad_captures_end: usize
+}
+
+impl MovePicker {
+    /// A picker for the main search. When in check every evasion is
+    /// generated at once, with the transposition table's move first.
+    pub fn new(board: &Board, tt_move: Option<Move>, killers: [Option<Move>; 2], countermove: Option<Move>) -> Self {
+        let tt_move = tt_move.filter(|&m| board.is_legal(m));
+        let stage = if board.in_check() {
+            Stage::GenerateEvasions
+        } else if tt_move.is_some() {
+            Stage::TtMove
+        } else {
+            Stage::GenerateCaptures
+        };
+        MovePicker {
+            stage,
+            tt_move,
+            killers,
+            countermove,
+            moves: MoveList::new(),
+            index: 0,
+            bad_captures_end: 0
+        }
+    }
+
+    /// A picker for quiescence search: captures that do not lose material,
+    /// or every evasion when in check.
+    pub fn quiescence(board: &Board) -> Self {
+        MovePicker {
+            stage: if board.in_check() { Stage::GenerateEvasions } else { Stage::GenerateQuiescence },
+            ..MovePicker::new(board, None, [None; 2], None)
+        }
+    }
+
+    pub fn next(&mut self, board: &Board, history: &History) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    return self.tt_move;
+                },
+                Stage::GenerateCaptures | Stage::GenerateQuiescence => {
+                    board.generate_captures(&mut self.moves);
+                    self.score_captures(board);
+                    self.stage = if self.stage == Stage::GenerateCaptures {
+                        Stage::GoodCaptures
+                    } else {
+                        Stage::QuiescenceCaptures
+                    };
+                },
+                Stage::GoodCaptures => {
+                    while let Some((m, _)) = self.moves.pick_best(self.index) {
+                        self.index += 1;
+                        if Some(m) == self.tt_move {
+                            continue;
+                        }
+                        if board.see_ge(m, 0) {
+                            return Some(m);
+                        }
+                        // Keep it for the last stage in a slot already used.
+                        self.moves.swap(self.bad_captures_end, self.index - 1);
+                        self.bad_captures_end += 1;
+                    }
+                    self.stage = Stage::FirstKiller;
+                },
+                Stage::FirstKiller | Stage::SecondKiller => {
+                    let killer = self.killers[(self.stage == Stage::SecondKiller) as usize];
+                    self.stage = if self.stage == Stage::FirstKiller { Stage::SecondKiller } else { Stage::Countermove };
+                    if let Some(m) = killer
+                        && self.is_fresh_quiet(board, m) {
+                        return Some(m);
+                    }
+                },
+                Stage::Countermove => {
+                    self.stage = Stage::GenerateQuiets;
+                    if let Some(m) = self.countermove
+                        && !self.killers.contains(&Some(m))
+                        && self.is_fresh_quiet(board, m) {
+                        return Some(m);
+                    }
+                },
+                Stage::GenerateQuiets => {
+                    let start = self.moves.len();
+                    board.generate_quiets(&mut self.moves);
+                    let us = board.side_to_move();
+                    for i in start..self.moves.len() {
+                        self.moves.scores_mut()[i] = history.butterfly(us, self.moves[i]);
+                    }
+                    self.index = start;
+                    self.stage = Stage::Quiets;
+                },
+                Stage::Quiets => {
+                    while let Some((m, _)) = self.moves.pick_best(self.index) {
+                        self.index += 1;
+                        let already_tried = Some(m) == self.tt_move
+                            || self.killers.contains(&Some(m))
+                            || Some(m) == self.countermove;
+                        if !already_tried {
+                            return Some(m);
+                        }
+                    }
+                    self.index = 0;
+                    self.stage = Stage::BadCaptures;
+                },
+                Stage::BadCaptures => {
+                    if self.index < self.bad_captures_end {
+                        self.index += 1;
+                        return Some(self.moves[self.index - 1]);
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::GenerateEvasions => {
+                    board.generate_legal_moves(&mut self.moves);
+                    let us = board.side_to_move();
+                    for i in 0..self.moves.len() {
+                        let m = self.moves[i];
+                        self.moves.scores_mut()[i] = if Some(m) == self.tt_move {
+                            i32::MAX
+                        } else if is_quiet(m) {
+                            history.butterfly(us, m)
+                        } else {
+                            EVASION_CAPTURE_BONUS + mvv_lva(board, m)
+                        };
+                    }
+                    self.stage = Stage::Evasions;
+                },
+                Stage::Evasions => {
+                    let (m, _) = self.moves.pick_best(self.index)?;
+                    self.index += 1;
+                    return Some(m);
+                },
+                Stage::QuiescenceCaptures => {
+                    while let Some((m, _)) = self.moves.pick_best(self.index) {
+                        self.index += 1;
+                        if board.see_ge(m, 0) {
+                            return Some(m);
+                        }
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::Done => return None
+            }
+        }
+    }
+
+    fn score_captures(&mut self, board: &Board) {
+        for i in 0..self.moves.len() {
+            self.moves.scores_mut()[i] = mvv_lva(board, self.moves[i]);
+        }
+    }
+
+    /// Whether a remembered quiet move is legal here and was not already
+    /// handed out as the transposition table's move.
+    fn is_fresh_quiet(&self, board: &Board, m: Move) -> bool {
+        Some(m) != self.tt_move && is_quiet(m) && board.is_legal(m)
+    }
+}
+
+/// Most valuable victim, then least valuable attacker. A promotion counts
+/// the piece gained as part of the victim.
+fn mvv_lva(board: &Board, m: Move) -> i32 {
+    let victim = match board.piece_on(m.target_sq()) {
+        Some(victim) if m.is_capture() => PIECE_VALUES[victim.type_of()],
+        None if m.is_en_passant() => PIECE_VALUES[PieceType::Pawn],
+        _ => 0
+    };
+    let promoted = m.promotion().map_or(0, |promotion| PIECE_VALUES[promotion]);
+    let attacker = board.piece_on(m.origin_sq()).expect("moves start on a piece");
+    10 * (victim + promoted) - PIECE_VALUES[attacker.type_of()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveFlag;
+
+    fn picked(board: &Board, mut picker: MovePicker, history: &History) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(m) = picker.next(board, history) {
+            moves.push(m);
+        }
+        moves
+    }
+
+    fn uci(board: &Board, moves: &[&str]) -> Vec<Move> {
+        moves.iter().map(|m| board.parse_uci_move(m, false).unwrap()).collect()
+    }
+
+    #[test]
+    fn every_legal_move_is_picked_once() {
+        let history = History::new();
+        for fen in [
+            Board::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/1b6/8/4r3/R3K2R w KQ - 0 1"
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let legal = board.legal_moves();
+            let tt_move = legal.last().copied();
+            let killers = [legal.first().copied(), Some(Move::new(SQ::A1, SQ::H8, MoveFlag::Quiet))];
+            let picker = MovePicker::new(&board, tt_move, killers, legal.get(1).copied());
+
+            let mut moves = picked(&board, picker, &history);
+            assert_eq!(moves[0], tt_move.unwrap(), "{}", fen);
+            assert_eq!(moves.len(), legal.len(), "{}", fen);
+            moves.sort_by_key(|m| m.to_string());
+            moves.dedup();
+            assert_eq!(moves.len(), legal.len(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn stages_come_in_order() {
+        // Nxb5 wins a pawn; Nxd5 and Qxd5 each lose material to exd5.
+        let board = Board::from_fen("4k3/8/4p3/1p1p4/8/2N5/8/3QK3 w - - 0 1").unwrap();
+        let mut history = History::new();
+        let quiet = board.parse_uci_move("e1f2", false).unwrap();
+        history.update_quiet(Color::White, 0, None, quiet, &[], 10);
+
+        let killer = board.parse_uci_move("d1d4", false).unwrap();
+        let picker = MovePicker::new(&board, board.parse_uci_move("d1d3", false).ok(), [Some(killer), None], None);
+        let moves = picked(&board, picker, &history);
+
+        assert_eq!(moves[..4], uci(&board, &["d1d3", "c3b5", "d1d4", "e1f2"]));
+        assert_eq!(moves[moves.len() - 2..], uci(&board, &["c3d5", "d1d5"]));
+        assert_eq!(moves.len(), board.legal_moves().len());
+    }
+
+    #[test]
+    fn quiescence_picks_only_winning_or_even_captures() {
+        let board = Board::from_fen("4k3/8/4p3/1p1p4/8/2N5/8/3QK3 w - - 0 1").unwrap();
+        let moves = picked(&board, MovePicker::quiescence(&board), &History::new());
+        assert_eq!(moves, uci(&board, &["c3b5"]));
+
+        // In check every evasion is searched.
+        let board = Board::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        let moves = picked(&board, MovePicker::quiescence(&board), &History::new());
+        assert_eq!(moves[0], board.parse_uci_move("e1e2", false).unwrap());
+        assert_eq!(moves.len(), board.legal_moves().len());
+    }
+
+    #[test]
+    fn illegal_remembered_moves_are_ignored() {
+        let board = Board::from_fen(Board::START_FEN).unwrap();
+        let bogus = Move::new(SQ::E2, SQ::E5, MoveFlag::Quiet);
+        let picker = MovePicker::new(&board, Some(bogus), [Some(bogus), None], Some(bogus));
+        let moves = picked(&board, picker, &History::new());
+        assert_eq!(moves.len(), 20);
+        assert!(!moves.contains(&bogus));
+    }
+
+    #[test]
+    fn history_rewards_cutoffs_and_punishes_the_rest() {
+        let mut history = History::new();
+        let good = Move::new(SQ::G1, SQ::F3, MoveFlag::Quiet);
+        let bad = Move::new(SQ::B1, SQ::A3, MoveFlag::Quiet);
+        let previous = Some((Piece::BLACK_PAWN, SQ::E5));
+        history.update_quiet(Color::White, 3, previous, good, &[bad, good], 4);
+
+        assert_eq!(history.killers(3), [Some(good), None]);
+        assert_eq!(history.countermove(previous), Some(good));
+        assert_eq!(history.butterfly(Color::White, good), 16);
+        assert_eq!(history.butterfly(Color::White, bad), -16);
+        assert_eq!(history.butterfly(Color::Black, good), 0);
+
+        for _ in 0..1000 {
+            history.update_quiet(Color::White, 3, previous, good, &[], 40);
+        }
+        assert!(history.butterfly(Color::White, good) <= MAX_HISTORY);
+        assert_eq!(history.killers(3), [Some(good), None]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::search::{Iteration, MATE_BOUND};
+
+/// Helper thread `n` searches with entry `(n - 1) % 20` of these tables,
+/// skipping the depths where `(depth + phase) / size` is odd, so helpers
+/// spread over neighbouring depths instead of all searching the same one.
+const SKIP_SIZE: [u32; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u32; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Added to every score before weighting so the lowest still gets a vote.
+const VOTE_BASE: i64 = 14;
+
+/// Whether thread `id` leaves the iteration at `depth` to the others. The
+/// main thread, id 0, searches every depth.
+pub fn skips_depth(id: usize, depth: u32) -> bool {
+    if id == 0 {
+        return false;
+    }
+    let entry = (id - 1) % SKIP_SIZE.len();
+    !((depth + SKIP_PHASE[entry]) / SKIP_SIZE[entry]).is_multiple_of(2)
+}
+
+/// A thread's node count, published now and then for the others to read.
+/// Each sits on its own cache line so threads updating theirs do not slow
+/// each other down.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+pub struct NodeCounter(AtomicU64);
+
+impl NodeCounter {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, nodes: u64) {
+        self.0.store(nodes, Ordering::Relaxed);
+    }
+}
+
+/// Picks the result to play from the deepest iteration each thread
+/// completed, the main thread's first. Every thread votes for its best
+/// move, weighted by its depth and by how its score compares to the
+/// others', and the first thread whose move has the most votes wins. A
+/// mate found by any thread beats the vote, and only a shorter mate beats
+/// that.
+pub fn vote(iterations: &[Iteration]) -> Option<&Iteration> {
+    let min_score = iterations.iter().map(|iteration| iteration.score).min()?;
+    let weight = |iteration: &Iteration| ((iteration.score - min_score) as i64 + VOTE_BASE) * iteration.depth as i64;
+    let votes = |iteration: &Iteration| -> i64 {
+        iterations
+            .iter()
+            .filter(|other| other.best_move == iteration.best_move)
+            .map(weight)
+            .sum()
+    };
+
+    let mut best = &iterations[0];
+    for iteration in &iterations[1..] {
+        let better = if best.score >= MATE_BOUND {
+            iteration.score > best.score
+        } else {
+            iteration.score >= MATE_BOUND || votes(iteration) > votes(best)
+        };
+        if better {
+            best = iteration;
+        }
+    }
+    Some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Move, MoveFlag, SQ};
+    use crate::search::MATE;
+
+    fn iteration(m: Move, depth: u32, score: i32) -> Iteration {
+        Iteration { depth, seldepth: depth, score, best_move: Some(m), pv: vec![m] }
+    }
+
+    #[test]
+    fn helpers_spread_over_depths() {
+        assert!((1..20).all(|depth| !skips_depth(0, depth)));
+
+        // The first two helpers take alternate depths between them.
+        let searched = |id| (1..=8).filter(|&depth| !skips_depth(id, depth)).collect::<Vec<_>>();
+        assert_eq!(searched(1), [2, 4, 6, 8]);
+        assert_eq!(searched(2), [1, 3, 5, 7]);
+        assert_eq!(searched(3), [1, 4, 5, 8]);
+        assert_eq!(searched(21), searched(1));
+    }
+
+    #[test]
+    fn most_voted_move_wins() {
+        let e4 = Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush);
+        let d4 = Move::new(SQ::D2, SQ::D4, MoveFlag::DoublePush);
+
+        assert!(vote(&[]).is_none());
+        assert_eq!(vote(&[iteration(e4, 10, 30)]).unwrap().best_move, Some(e4));
+
+        // Two helpers agreeing outvote the main thread.
+        let iterations = [iteration(e4, 10, 30), iteration(d4, 10, 25), iteration(d4, 9, 28)];
+        assert_eq!(vote(&iterations).unwrap().best_move, Some(d4));
+
+        // A deeper, better-scoring main thread holds its own, and the
+        // main thread wins ties.
+        let iterations = [iteration(e4, 14, 60), iteration(d4, 10, 25), iteration(d4, 9, 28)];
+        assert_eq!(vote(&iterations).unwrap().best_move, Some(e4));
+        let iterations = [iteration(e4, 10, 30), iteration(d4, 10, 30)];
+        assert_eq!(vote(&iterations).unwrap().best_move, Some(e4));
+    }
+
+    #[test]
+    fn a_mate_beats_the_vote() {
+        let e4 = Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush);
+        let d4 = Move::new(SQ::D2, SQ::D4, MoveFlag::DoublePush);
+        let c4 = Move::new(SQ::C2, SQ::C4, MoveFlag::DoublePush);
+
+        let iterations = [iteration(e4, 12, 40), iteration(e4, 12, 40), iteration(d4, 8, MATE - 5)];
+        assert_eq!(vote(&iterations).unwrap().best_move, Some(d4));
+
+        let iterations = [iteration(e4, 12, MATE - 7), iteration(d4, 8, MATE - 5), iteration(c4, 14, MATE - 9)];
+        assert_eq!(vote(&iterations).unwrap().best_move, Some(d4));
+    }
+}
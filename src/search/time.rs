@@ -0,0 +1,275 @@
+use std::time::{Duration, Instant};
+
+use crate::board::Move;
+use crate::search::SearchLimits;
+
+/// How many moves the remaining time is spread over when the time control
+/// does not say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The most of the remaining time one move may plan to use, and the most it
+/// may use at all.
+const SOFT_FRACTION: f64 = 0.5;
+const HARD_FRACTION: f64 = 0.8;
+
+/// How far past its planned time a move may run while the search is
+/// unsettled.
+const HARD_TO_SOFT_RATIO: u32 = 4;
+
+/// A score drop of this many centipawns between iterations earns the most
+/// extra time.
+const SCORE_DROP_SPAN: i32 = 100;
+
+/// The time left on the clock of the side to move, as `go` reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    pub time: Duration,
+    pub increment: Duration,
+    pub moves_to_go: Option<u32>
+}
+
+/// Where a `TimeManager` reads the time spent so far.
+pub trait Clock: Send {
+    fn elapsed(&self) -> Duration;
+}
+
+/// Measures real time from when it was created.
+pub struct WallClock {
+    start: Instant
+}
+
+impl WallClock {
+    pub fn start() -> Self {
+        WallClock { start: Instant::now() }
+    }
+}
+
+impl Clock for WallClock {
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Decides how long a search may think. The soft limit is when no new
+/// iteration should start, and grows while the best move keeps changing or
+/// the score falls; the hard limit stops the search wherever it is.
+pub struct TimeManager {
+    clock: Box<dyn Clock>,
+    soft: Option<Duration>,
+    hard: Option<Duration>,
+    single_move: bool,
+    best_move: Option<Move>,
+    score: Option<i32>,
+    /// Recent best move changes, halved each iteration so old ones fade.
+    instability: f64,
+    score_drop: i32
+}
+
+impl TimeManager {
+    /// Plans the search from a fixed move time or the clock in `limits`,
+    /// keeping the move overhead in reserve for communication delays. With
+    /// a single legal move on a running clock there is nothing to think
+    /// about, so the search stops after its first iteration.
+    pub fn new(limits: &SearchLimits, legal_moves: usize, clock: Box<dyn Clock>) -> Self {
+        let overhead = limits.move_overhead;
+        let (soft, hard) = if let Some(movetime) = limits.movetime {
+            let movetime = movetime.saturating_sub(overhead);
+            (Some(movetime), Some(movetime))
+        } else if let Some(control) = limits.time_control {
+            let available = control.time.saturating_sub(overhead);
+            let moves = control.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let soft = (available / moves + control.increment * 3 / 4).min(available.mul_f64(SOFT_FRACTION));
+            let hard = (soft * HARD_TO_SOFT_RATIO).min(available.mul_f64(HARD_FRACTION));
+            (Some(soft), Some(hard))
+        } else {
+            (None, None)
+        };
+
+        TimeManager {
+            clock,
+            soft,
+            hard,
+            single_move: legal_moves == 1 && limits.time_control.is_some() && limits.movetime.is_none(),
+            best_move: None,
+            score: None,
+            instability: 0.0,
+            score_drop: 0
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    pub fn soft_limit(&self) -> Option<Duration> {
+        let soft = self.soft?;
+        let instability = 1.0 + self.instability.min(2.0) * 0.5;
+        let score_drop = 1.0 + self.score_drop.clamp(0, SCORE_DROP_SPAN) as f64 / SCORE_DROP_SPAN as f64;
+        Some(soft.mul_f64(instability * score_drop).min(self.hard.unwrap_or(Duration::MAX)))
+    }
+
+    pub fn hard_limit(&self) -> Option<Duration> {
+        self.hard
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn on_iteration(&mut self, best_move: Option<Move>, score: i32) {
+        self.instability *= 0.5;
+        if self.best_move.is_some() && best_move != self.best_move {
+            self.instability += 1.0;
+        }
+        self.score_drop = self.score.map_or(0, |previous| previous - score);
+        self.best_move = best_move;
+        self.score = Some(score);
+    }
+
+    /// Whether to stop rather than start another iteration.
+    pub fn should_stop_iterating(&self) -> bool {
+        self.single_move || self.soft_limit().is_some_and(|soft| self.elapsed() >= soft)
+    }
+
+    /// Whether the search must stop immediately.
+    pub fn out_of_time(&self) -> bool {
+        self.hard.is_some_and(|hard| self.elapsed() >= hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::board::{MoveFlag, SQ};
+
+    /// A clock the test moves forward by hand.
+    #[derive(Clone, Default)]
+    struct FakeClock(Arc<Mutex<Duration>>);
+
+    impl FakeClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn elapsed(&self) -> Duration {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn manager(control: TimeControl, overhead: u64) -> (TimeManager, FakeClock) {
+        let limits = SearchLimits {
+            time_control: Some(control),
+            move_overhead: Duration::from_millis(overhead),
+            ..SearchLimits::default()
+        };
+        let clock = FakeClock::default();
+        (TimeManager::new(&limits, 20, Box::new(clock.clone())), clock)
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn sudden_death_spreads_the_clock() {
+        let (tm, _) = manager(TimeControl { time: secs(60), ..TimeControl::default() }, 0);
+        assert_eq!(tm.soft_limit(), Some(secs(2)));
+        assert_eq!(tm.hard_limit(), Some(secs(8)));
+    }
+
+    #[test]
+    fn increment_and_overhead() {
+        let control = TimeControl { time: secs(30), increment: secs(2), moves_to_go: None };
+        let (tm, _) = manager(control, 0);
+        assert_eq!(tm.soft_limit(), Some(secs(1) + Duration::from_millis(1500)));
+
+        // The overhead comes off the clock before anything is planned.
+        let (tm, _) = manager(TimeControl { time: Duration::from_millis(30_300), ..control }, 300);
+        assert_eq!(tm.soft_limit(), Some(secs(1) + Duration::from_millis(1500)));
+
+        // A low clock is never planned away, whatever the increment.
+        let (tm, _) = manager(TimeControl { time: secs(1), increment: secs(5), moves_to_go: None }, 0);
+        assert_eq!(tm.soft_limit(), Some(Duration::from_millis(500)));
+        assert_eq!(tm.hard_limit(), Some(Duration::from_millis(800)));
+    }
+
+    #[test]
+    fn moves_to_go() {
+        let (tm, _) = manager(TimeControl { time: secs(100), increment: secs(0), moves_to_go: Some(10) }, 0);
+        assert_eq!(tm.soft_limit(), Some(secs(10)));
+        assert_eq!(tm.hard_limit(), Some(secs(40)));
+
+        let (tm, _) = manager(TimeControl { time: secs(100), increment: secs(0), moves_to_go: Some(1) }, 0);
+        assert_eq!(tm.soft_limit(), Some(secs(50)));
+        assert_eq!(tm.hard_limit(), Some(secs(80)));
+    }
+
+    #[test]
+    fn fixed_move_time_and_no_limits() {
+        let limits = SearchLimits {
+            movetime: Some(secs(3)),
+            move_overhead: Duration::from_millis(100),
+            ..SearchLimits::default()
+        };
+        let tm = TimeManager::new(&limits, 1, Box::new(FakeClock::default()));
+        assert_eq!(tm.soft_limit(), Some(Duration::from_millis(2900)));
+        assert_eq!(tm.hard_limit(), Some(Duration::from_millis(2900)));
+        assert!(!tm.should_stop_iterating());
+
+        let tm = TimeManager::new(&SearchLimits::default(), 1, Box::new(FakeClock::default()));
+        assert_eq!(tm.hard_limit(), None);
+        assert!(!tm.should_stop_iterating());
+        assert!(!tm.out_of_time());
+    }
+
+    #[test]
+    fn limits_follow_the_clock() {
+        let (tm, clock) = manager(TimeControl { time: secs(60), ..TimeControl::default() }, 0);
+        assert!(!tm.should_stop_iterating());
+        clock.advance(secs(2));
+        assert!(tm.should_stop_iterating());
+        assert!(!tm.out_of_time());
+        clock.advance(secs(6));
+        assert!(tm.out_of_time());
+    }
+
+    #[test]
+    fn instability_and_score_drops_extend_the_soft_limit() {
+        let a = Some(Move::new(SQ::E2, SQ::E4, MoveFlag::DoublePush));
+        let b = Some(Move::new(SQ::D2, SQ::D4, MoveFlag::DoublePush));
+
+        let (mut tm, _) = manager(TimeControl { time: secs(60), ..TimeControl::default() }, 0);
+        tm.on_iteration(a, 20);
+        tm.on_iteration(a, 25);
+        assert_eq!(tm.soft_limit(), Some(secs(2)));
+
+        tm.on_iteration(b, 25);
+        assert_eq!(tm.soft_limit(), Some(secs(3)));
+        tm.on_iteration(b, 25);
+        assert_eq!(tm.soft_limit(), Some(Duration::from_millis(2500)));
+
+        tm.on_iteration(b, -75);
+        assert_eq!(tm.soft_limit(), Some(Duration::from_millis(4500)));
+
+        // However unsettled, the hard limit still holds.
+        let (mut tm, _) = manager(TimeControl { time: secs(100), increment: secs(0), moves_to_go: Some(1) }, 0);
+        tm.on_iteration(a, 0);
+        tm.on_iteration(b, -500);
+        assert_eq!(tm.soft_limit(), Some(secs(80)));
+    }
+
+    #[test]
+    fn single_legal_move_stops_after_one_iteration() {
+        let limits = SearchLimits {
+            time_control: Some(TimeControl { time: secs(60), ..TimeControl::default() }),
+            ..SearchLimits::default()
+        };
+        let tm = TimeManager::new(&limits, 1, Box::new(FakeClock::default()));
+        assert!(tm.should_stop_iterating());
+
+        // Analysis without a clock still searches the only move.
+        let tm = TimeManager::new(&SearchLimits::default(), 1, Box::new(FakeClock::default()));
+        assert!(!tm.should_stop_iterating());
+    }
+}
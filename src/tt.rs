@@ -0,0 +1,299 @@
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+use crate::board::Move;
+
+/// The size of the table before the `Hash` option changes it.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// How a stored score relates to the true score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The search found the true score.
+    Exact,
+    /// The search failed high, so the true score is at least this.
+    Lower,
+    /// The search failed low, so the true score is at most this.
+    Upper
+}
+
+/// What the table remembers about a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound
+}
+
+/// Two slots sharing a cache line: the first keeps the deepest search of
+/// the current generation, the second takes whatever the first turns away.
+#[derive(Default)]
+struct Bucket {
+    slots: [Slot; 2]
+}
+
+/// One entry stored as the key xored with the data, and the data. A write
+/// racing with another thread leaves a pair that no longer xors to the key,
+/// so torn entries are simply misses and no lock is needed.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        (self.key.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    fn store(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// A hash table of search results keyed by Zobrist hash, shared by every
+/// search thread.
+pub struct TranspositionTable {
+    buckets: Box<[Bucket]>,
+    generation: AtomicU8
+}
+
+impl TranspositionTable {
+    /// Creates a table using about `mb` megabytes, and at least one bucket.
+    pub fn new(mb: usize) -> Self {
+        let count = (mb * 1024 * 1024 / size_of::<Bucket>()).max(1);
+        TranspositionTable {
+            buckets: (0..count).map(|_| Bucket::default()).collect(),
+            generation: AtomicU8::new(0)
+        }
+    }
+
+    /// Reallocates the table at the new size, discarding its contents.
+    pub fn resize(&mut self, mb: usize) {
+        *self = TranspositionTable::new(mb);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| &bucket.slots) {
+            slot.store(0, 0);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Starts a new generation, so entries left by earlier searches are
+    /// replaced before fresh ones.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Hints the CPU to start loading the bucket for `key`, ahead of a probe.
+    pub fn prefetch(&self, key: u64) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+            let bucket: *const Bucket = self.bucket(key);
+            // SAFETY: prefetching is only a hint and never faults, and the
+            // pointer is to a live bucket regardless.
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(bucket.cast()) };
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = key;
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        self.bucket(key).slots.iter().find_map(|slot| {
+            let (slot_key, data) = slot.load();
+            (slot_key == key && data != 0).then(|| unpack_entry(data))
+        })
+    }
+
+    /// Stores a search result. A result without a move keeps the move
+    /// already stored for the same position.
+    pub fn store(&self, key: u64, entry: TtEntry) {
+        let generation = self.generation();
+        let [deep, always] = &self.bucket(key).slots;
+
+        let (deep_key, deep_data) = deep.load();
+        let slot = if deep_data == 0
+            || deep_key == key
+            || unpack_generation(deep_data) != generation
+            || entry.depth >= unpack_entry(deep_data).depth {
+            deep
+        } else {
+            always
+        };
+
+        let mut entry = entry;
+        if entry.best_move.is_none() {
+            let (slot_key, data) = slot.load();
+            if slot_key == key && data != 0 {
+                entry.best_move = unpack_entry(data).best_move;
+            }
+        }
+        slot.store(key, pack_entry(entry, generation));
+    }
+
+    /// How full the table is in permille, counting only entries from the
+    /// current search among the first thousand slots.
+    pub fn hashfull(&self) -> u32 {
+        let generation = self.generation();
+        let sample: Vec<u64> = self.buckets
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .take(1000)
+            .map(|slot| slot.load().1)
+            .collect();
+        let used = sample.iter().filter(|&&data| data != 0 && unpack_generation(data) == generation).count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed) & GENERATION_MASK
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        // Multiplying spreads the key's high bits over the bucket range
+        // without a division.
+        let index = ((key as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
+
+// Data layout, from the low bits: move (16), score (16), depth (8),
+// bound (2), generation (6). A used entry always has a nonzero bound.
+const GENERATION_MASK: u8 = 0x3f;
+
+fn pack_entry(entry: TtEntry, generation: u8) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3
+    };
+    pack_move(entry.best_move) as u64
+        | (entry.score as u16 as u64) << 16
+        | (entry.depth as u64) << 32
+        | (bound as u64) << 40
+        | ((generation & GENERATION_MASK) as u64) << 42
+}
+
+fn unpack_entry(data: u64) -> TtEntry {
+    TtEntry {
+        best_move: unpack_move(data as u16),
+        score: (data >> 16) as u16 as i16,
+        depth: (data >> 32) as u8,
+        bound: match (data >> 40) & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper
+        }
+    }
+}
+
+fn unpack_generation(data: u64) -> u8 {
+    (data >> 42) as u8 & GENERATION_MASK
+}
+
+/// Stores a move in its 16-bit form. No move is stored as 0, which would
+/// be a quiet move from a1 to a1.
+fn pack_move(m: Option<Move>) -> u16 {
+    m.map_or(0, Move::to_u16)
+}
+
+fn unpack_move(bits: u16) -> Option<Move> {
+    if bits == 0 {
+        return None;
+    }
+    Move::from_u16(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{MoveFlag, SQ};
+
+    fn entry(depth: u8, score: i16) -> TtEntry {
+        TtEntry {
+            best_move: Some(Move::new(SQ::E7, SQ::E8, MoveFlag::QueenPromotion)),
+            score,
+            depth,
+            bound: Bound::Lower
+        }
+    }
+
+    #[test]
+    fn store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.probe(42), None);
+
+        tt.store(42, entry(5, -31_000));
+        assert_eq!(tt.probe(42), Some(entry(5, -31_000)));
+        assert_eq!(tt.probe(43), None);
+
+        tt.clear();
+        assert_eq!(tt.probe(42), None);
+    }
+
+    #[test]
+    fn storing_without_a_move_keeps_the_old_one() {
+        let tt = TranspositionTable::new(1);
+        tt.store(7, entry(3, 10));
+        tt.store(7, TtEntry { best_move: None, bound: Bound::Upper, ..entry(4, -5) });
+        let stored = tt.probe(7).unwrap();
+        assert_eq!(stored.best_move, entry(3, 10).best_move);
+        assert_eq!((stored.depth, stored.score, stored.bound), (4, -5, Bound::Upper));
+    }
+
+    #[test]
+    fn deep_entries_survive_shallow_ones() {
+        // With one bucket every key collides.
+        let tt = TranspositionTable::new(0);
+        tt.store(1, entry(8, 0));
+        tt.store(2, entry(2, 0));
+        tt.store(3, entry(1, 0));
+        assert!(tt.probe(1).is_some());
+        assert!(tt.probe(2).is_none());
+        assert!(tt.probe(3).is_some());
+
+        // An old generation's entry gives way even to a shallow one.
+        tt.new_search();
+        tt.store(4, entry(1, 0));
+        assert!(tt.probe(1).is_none());
+        assert!(tt.probe(4).is_some());
+    }
+
+    #[test]
+    fn hashfull_counts_the_current_search() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for key in 0..500_000u64 {
+            tt.store(key.wrapping_mul(0x9e37_79b9_7f4a_7c15), entry((key % 8) as u8, 0));
+        }
+        assert!(tt.hashfull() > 900);
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+
+        tt.resize(2);
+        assert_eq!(tt.buckets.len(), 2 * 1024 * 1024 / size_of::<Bucket>());
+    }
+
+    #[test]
+    fn moves_round_trip_through_packing() {
+        for m in [
+            None,
+            Some(Move::new(SQ::A1, SQ::H8, MoveFlag::Quiet)),
+            Some(Move::new(SQ::B7, SQ::A8, MoveFlag::KnightPromotion)),
+            Some(Move::new(SQ::H2, SQ::H1, MoveFlag::QueenPromotion))
+        ] {
+            assert_eq!(unpack_move(pack_move(m)), m);
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::board::{Board, Color, GameState};
+use crate::search::{STACK_SIZE, SearchFeatures, SearchLimits, TimeControl, search};
+use crate::tt::{DEFAULT_SIZE_MB, TranspositionTable};
+
+/// The largest `Hash` size accepted, in megabytes.
+const MAX_HASH_MB: usize = 65536;
+
+/// The `Move Overhead` default and maximum, in milliseconds.
+const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+
+/// The largest `Threads` count accepted.
+const MAX_THREADS: usize = 1024;
+
+/// The check options switching parts of the search on and off, for testing
+/// each one's worth.
+const FEATURE_OPTIONS: [&str; 6] = [
+    "NullMovePruning",
+    "LateMoveReductions",
+    "ReverseFutilityPruning",
+    "FutilityPruning",
+    "LateMovePruning",
+    "CheckExtensions"
+];
+
+/// The switch in `features` for the option `name`, in lowercase.
+fn feature<'a>(features: &'a mut SearchFeatures, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "nullmovepruning"        => Some(&mut features.null_move),
+        "latemovereductions"     => Some(&mut features.late_move_reductions),
+        "reversefutilitypruning" => Some(&mut features.reverse_futility),
+        "futilitypruning"        => Some(&mut features.futility),
+        "latemovepruning"        => Some(&mut features.late_move_pruning),
+        "checkextensions"        => Some(&mut features.check_extensions),
+        _ => None
+    }
+}
+
+/// The limits given to a `go` command. Unset limits are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub time: [Option<Duration>; Color::COUNT],
+    pub inc: [Option<Duration>; Color::COUNT],
+    pub movestogo: Option<u32>,
+    pub infinite: bool
+}
+
+impl GoParams {
+    /// Parses the arguments following `go`, ignoring tokens it does not know.
+    pub fn parse(args: &[&str]) -> Result<GoParams, String> {
+        fn value<T: std::str::FromStr>(args: &[&str], i: usize) -> Result<T, String> {
+            let token = args.get(i + 1).ok_or_else(|| format!("go {} requires a value", args[i]))?;
+            token.parse().map_err(|_| format!("invalid value '{}' for go {}", token, args[i]))
+        }
+        let millis = |i| value::<u64>(args, i).map(Duration::from_millis);
+
+        let mut params = GoParams::default();
+        let mut i = 0;
+        while i < args.len() {
+            let mut consumed = 2;
+            match args[i] {
+                "depth"     => params.depth = Some(value(args, i)?),
+                "nodes"     => params.nodes = Some(value(args, i)?),
+                "movetime"  => params.movetime = Some(millis(i)?),
+                "wtime"     => params.time[Color::White] = Some(millis(i)?),
+                "btime"     => params.time[Color::Black] = Some(millis(i)?),
+                "winc"      => params.inc[Color::White] = Some(millis(i)?),
+                "binc"      => params.inc[Color::Black] = Some(millis(i)?),
+                "movestogo" => params.movestogo = Some(value(args, i)?),
+                "infinite"  => {
+                    params.infinite = true;
+                    consumed = 1;
+                },
+                _ => consumed = 1
+            }
+            i += consumed;
+        }
+        Ok(params)
+    }
+}
+
+/// Parses the arguments following `position` into the game they describe,
+/// reading castles as the king taking its rook if `chess960` is set.
+pub fn parse_position(args: &[&str], chess960: bool) -> Result<GameState, String> {
+    let (board, rest) = match args.first() {
+        Some(&"startpos") => (Board::from_fen(Board::START_FEN).expect("start position is valid"), &args[1..]),
+        Some(&"fen") => {
+            let end = args.iter().position(|&token| token == "moves").unwrap_or(args.len());
+            let fen = args[1..end].join(" ");
+            let board = Board::from_fen(&fen).map_err(|e| format!("invalid fen: {}", e))?;
+            (board, &args[end..])
+        },
+        _ => return Err("position requires startpos or fen".to_string())
+    };
+
+    let mut state = GameState::new(board);
+    if let Some((&"moves", moves)) = rest.split_first() {
+        for token in moves {
+            let m = state.board().parse_uci_move(token, chess960).map_err(|e| format!("invalid move {}: {}", token, e))?;
+            state.make_move(m);
+        }
+    }
+    Ok(state)
+}
+
+impl GoParams {
+    /// The search limits for these parameters with `us` to move. An
+    /// infinite search ignores the clock.
+    pub fn search_limits(&self, us: Color, move_overhead: Duration) -> SearchLimits {
+        let time_control = self.time[us].map(|time| TimeControl {
+            time,
+            increment: self.inc[us].unwrap_or_default(),
+            moves_to_go: self.movestogo
+        });
+
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            movetime: self.movetime.filter(|_| !self.infinite),
+            time_control: time_control.filter(|_| !self.infinite),
+            move_overhead,
+            threads: 1,
+            features: SearchFeatures::default()
+        }
+    }
+}
+
+/// A UCI session writing its responses to `W`. Searches run on their own
+/// thread so `stop` and `isready` are answered while they think.
+pub struct Uci<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    state: GameState,
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    infinite: bool,
+    move_overhead: Duration,
+    threads: usize,
+    features: SearchFeatures,
+    /// Whether castles are read and written as the king taking its rook.
+    chess960: bool
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(output: W) -> Self {
+        Uci {
+            output: Arc::new(Mutex::new(output)),
+            state: GameState::default(),
+            tt: Arc::new(TranspositionTable::new(DEFAULT_SIZE_MB)),
+            stop: Arc::new(AtomicBool::new(false)),
+            search: None,
+            infinite: false,
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            threads: 1,
+            features: SearchFeatures::default(),
+            chess960: false
+        }
+    }
+
+    /// Reads commands until `quit` or the end of the input, then returns the
+    /// output once any running search has finished. At the end of the input
+    /// a search with limits is left to finish, so scripts piped to the engine
+    /// get their answer; `quit` stops it at once.
+    pub fn run(mut self, input: impl BufRead) -> W {
+        let mut quit = false;
+        for line in input.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if !self.handle(&line) {
+                quit = true;
+                break;
+            }
+        }
+
+        if quit || self.infinite {
+            self.stop_search();
+        } else if let Some(search) = self.search.take() {
+            search.join().expect("search thread panicked");
+        }
+
+        let output = Arc::try_unwrap(self.output).unwrap_or_else(|_| unreachable!("search thread has been joined"));
+        output.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Handles one command line, returning `false` on `quit`.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match command {
+            "uci" => {
+                self.send(&format!("id name chess {}", env!("CARGO_PKG_VERSION")));
+                self.send("id author the chess authors");
+                self.send(&format!("option name Hash type spin default {} min 1 max {}", DEFAULT_SIZE_MB, MAX_HASH_MB));
+                self.send(&format!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS));
+                self.send(&format!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS
+                ));
+                self.send("option name UCI_Chess960 type check default false");
+                for name in FEATURE_OPTIONS {
+                    self.send(&format!("option name {} type check default true", name));
+                }
+                self.send("uciok");
+            },
+            "isready" => self.send("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.state = GameState::default();
+                self.tt.clear();
+            },
+            "position" => {
+                self.stop_search();
+                match parse_position(args, self.chess960) {
+                    Ok(state) => self.state = state,
+                    Err(message) => self.send(&format!("info string {}", message))
+                }
+            },
+            "go" => {
+                self.stop_search();
+                match GoParams::parse(args) {
+                    Ok(params) => self.start_search(params),
+                    Err(message) => self.send(&format!("info string {}", message))
+                }
+            },
+            "stop" => self.stop_search(),
+            "setoption" => self.set_option(args),
+            "quit" => return false,
+            _ => self.send(&format!("info string unknown command '{}'", command))
+        }
+        true
+    }
+
+    fn set_option(&mut self, args: &[&str]) {
+        let name_end = args.iter().position(|&token| token == "value").unwrap_or(args.len());
+        let name = args.get(1..name_end).map(|name| name.join(" ")).unwrap_or_default();
+        let value = args.get(name_end + 1..).map(|value| value.join(" ")).unwrap_or_default();
+
+        // UCI option names are case insensitive.
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => {
+                    self.stop_search();
+                    Arc::get_mut(&mut self.tt).expect("no search holds the table once stopped").resize(mb);
+                },
+                _ => self.send(&format!("info string invalid value '{}' for option Hash", value))
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) if (1..=MAX_THREADS).contains(&threads) => self.threads = threads,
+                _ => self.send(&format!("info string invalid value '{}' for option Threads", value))
+            },
+            "move overhead" => match value.parse::<u64>() {
+                Ok(ms) if ms <= MAX_MOVE_OVERHEAD_MS => self.move_overhead = Duration::from_millis(ms),
+                _ => self.send(&format!("info string invalid value '{}' for option Move Overhead", value))
+            },
+            "uci_chess960" => match value.parse::<bool>() {
+                Ok(chess960) => self.chess960 = chess960,
+                Err(_) => self.send(&format!("info string invalid value '{}' for option UCI_Chess960", value))
+            },
+            lowercase => match (feature(&mut self.features, lowercase), value.parse::<bool>()) {
+                (Some(enabled), Ok(value)) => *enabled = value,
+                (Some(_), Err(_)) => self.send(&format!("info string invalid value '{}' for option {}", value, name)),
+                (None, _) => self.send(&format!("info string unknown option '{}'", name))
+            }
+        }
+    }
+
+    fn start_search(&mut self, params: GoParams) {
+        self.stop.store(false, Ordering::SeqCst);
+        self.infinite = params.infinite;
+        let state = self.state.clone();
+        let tt = Arc::clone(&self.tt);
+        let stop = Arc::clone(&self.stop);
+        let output = Arc::clone(&self.output);
+        let chess960 = self.chess960;
+        let limits = SearchLimits {
+            threads: self.threads,
+            features: self.features,
+            ..params.search_limits(state.board().side_to_move(), self.move_overhead)
+        };
+
+        let search_thread = std::thread::Builder::new().name("search".to_string()).stack_size(STACK_SIZE);
+        self.search = Some(search_thread.spawn(move || {
+            let result = search(&state, &limits, &tt, &stop, |info| {
+                send(&output, &format!("info {}", info.to_uci(chess960)));
+            });
+
+            // In infinite mode the best move may only be sent after `stop`.
+            while params.infinite && !stop.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            let best = result.best_move.map_or("0000".to_string(), |m| m.to_uci(chess960));
+            send(&output, &format!("bestmove {}", best));
+        }).expect("failed to spawn the search thread"));
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(search) = self.search.take() {
+            search.join().expect("search thread panicked");
+        }
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap_or_else(|e| e.into_inner());
+    // A closed output leaves nothing useful to do, so write errors are ignored.
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str) -> String {
+        let output = Uci::new(Vec::new()).run(script.as_bytes());
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let output = run("uci\nisready\nquit\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("id name chess"));
+        assert!(lines.contains(&"option name Hash type spin default 16 min 1 max 65536"));
+        assert!(lines.contains(&"option name Threads type spin default 1 min 1 max 1024"));
+        assert!(lines.contains(&"option name UCI_Chess960 type check default false"));
+        assert_eq!(lines[lines.len() - 2..], ["uciok", "readyok"]);
+    }
+
+    #[test]
+    fn hash_option_resizes_the_table() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name Hash value 1");
+        assert!(uci.output.lock().unwrap().is_empty());
+
+        uci.handle("setoption name hash value 0");
+        uci.handle("setoption name Hash value lots");
+        let output = String::from_utf8(uci.run("".as_bytes())).unwrap();
+        assert!(output.contains("info string invalid value '0' for option Hash"));
+        assert!(output.contains("info string invalid value 'lots' for option Hash"));
+    }
+
+    #[test]
+    fn go_answers_with_a_legal_move() {
+        let output = run("position startpos moves e2e4 e7e5\ngo depth 1");
+        let best = output.lines().find_map(|line| line.strip_prefix("bestmove ")).unwrap();
+
+        let state = parse_position(&["startpos", "moves", "e2e4", "e7e5"], false).unwrap();
+        assert!(state.board().parse_uci_move(best, false).is_ok());
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!String::from_utf8_lossy(&uci.output.lock().unwrap()).contains("bestmove"));
+
+        uci.handle("stop");
+        let output = String::from_utf8(uci.run("".as_bytes())).unwrap();
+        assert_eq!(output.lines().filter(|line| line.starts_with("bestmove ")).count(), 1);
+    }
+
+    #[test]
+    fn position_from_fen_with_moves() {
+        let state = parse_position(&[
+            "fen", "4k3/8/8/8/8/8/8/R3K3", "w", "Q", "-", "0", "1", "moves", "e1c1", "e8e7"
+        ], false).unwrap();
+        assert_eq!(state.board().to_fen(), "8/4k3/8/8/8/8/8/2KR4 w - - 2 2");
+        assert_eq!(state.moves().len(), 2);
+
+        assert!(parse_position(&["startpos", "moves", "e2e5"], false).is_err());
+        assert!(parse_position(&["fen", "8/8/8/8/8/8/8/8", "w", "-", "-"], false).is_err());
+        assert!(parse_position(&[], false).is_err());
+    }
+
+    #[test]
+    fn mated_position_has_no_best_move() {
+        let output = run("position fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1\ngo\nquit\n");
+        assert!(output.contains("bestmove 0000"));
+    }
+
+    #[test]
+    fn go_params() {
+        let params = GoParams::parse(&[
+            "wtime", "60000", "btime", "50000", "winc", "1000", "binc", "500", "movestogo", "20", "depth", "7"
+        ]).unwrap();
+        assert_eq!(params.time, [Some(Duration::from_secs(60)), Some(Duration::from_secs(50))]);
+        assert_eq!(params.inc, [Some(Duration::from_secs(1)), Some(Duration::from_millis(500))]);
+        assert_eq!(params.movestogo, Some(20));
+        assert_eq!(params.depth, Some(7));
+        assert!(!params.infinite);
+
+        let params = GoParams::parse(&["infinite", "nodes", "1000", "movetime", "250"]).unwrap();
+        assert!(params.infinite);
+        assert_eq!(params.nodes, Some(1000));
+        assert_eq!(params.movetime, Some(Duration::from_millis(250)));
+
+        assert!(GoParams::parse(&["depth"]).is_err());
+        assert!(GoParams::parse(&["depth", "x"]).is_err());
+    }
+
+    #[test]
+    fn search_limits_from_clock() {
+        let overhead = Duration::from_millis(10);
+        let params = GoParams::parse(&["wtime", "30000", "btime", "1000", "winc", "1000", "movestogo", "5"]).unwrap();
+        let limits = params.search_limits(Color::White, overhead);
+        assert_eq!(limits.time_control, Some(TimeControl {
+            time: Duration::from_secs(30),
+            increment: Duration::from_secs(1),
+            moves_to_go: Some(5)
+        }));
+        assert_eq!(limits.move_overhead, overhead);
+        assert_eq!(limits.movetime, None);
+        let limits = params.search_limits(Color::Black, overhead);
+        assert_eq!(limits.time_control.unwrap().increment, Duration::ZERO);
+
+        let params = GoParams::parse(&["movetime", "200", "depth", "3"]).unwrap();
+        let limits = params.search_limits(Color::White, overhead);
+        assert_eq!(limits.movetime, Some(Duration::from_millis(200)));
+        assert_eq!(limits.time_control, None);
+        assert_eq!(limits.depth, Some(3));
+
+        let params = GoParams::parse(&["infinite", "wtime", "1000"]).unwrap();
+        assert_eq!(params.search_limits(Color::White, overhead).time_control, None);
+    }
+
+    #[test]
+    fn move_overhead_option() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name Move Overhead value 250");
+        assert_eq!(uci.move_overhead, Duration::from_millis(250));
+
+        uci.handle("setoption name Move Overhead value -1");
+        assert_eq!(uci.move_overhead, Duration::from_millis(250));
+        let output = String::from_utf8(uci.run("".as_bytes())).unwrap();
+        assert!(output.contains("info string invalid value '-1' for option Move Overhead"));
+    }
+
+    #[test]
+    fn threads_option() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name Threads value 3");
+        assert_eq!(uci.threads, 3);
+
+        uci.handle("setoption name Threads value 0");
+        assert_eq!(uci.threads, 3);
+        uci.handle("position startpos");
+        uci.handle("go depth 4");
+        let output = String::from_utf8(uci.run("".as_bytes())).unwrap();
+        assert!(output.contains("info string invalid value '0' for option Threads"));
+        assert!(output.contains("bestmove "));
+    }
+
+    #[test]
+    fn chess960_option_reads_castles_as_king_takes_rook() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name UCI_Chess960 value true");
+        assert!(uci.chess960);
+
+        // The king on f1 castles with the rook on g1, and they swap squares.
+        uci.handle("position fen b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9 moves f1g1");
+        assert_eq!(uci.state.board().to_fen(), "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRRKB b - - 2 9");
+
+        uci.handle("setoption name UCI_Chess960 value yes");
+        assert!(uci.chess960);
+        uci.handle("go depth 2");
+        let output = String::from_utf8(uci.run("".as_bytes())).unwrap();
+        assert!(output.contains("info string invalid value 'yes' for option UCI_Chess960"));
+        assert!(output.contains("bestmove "));
+    }
+
+    #[test]
+    fn castle_notation_follows_chess960_option() {
+        let castle = |m, chess960| {
+            parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", m], chess960)
+        };
+        let castled = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4";
+
+        assert_eq!(castle("e1g1", false).unwrap().board().to_fen(), castled);
+        assert!(castle("e1h1", false).is_err());
+        assert_eq!(castle("e1h1", true).unwrap().board().to_fen(), castled);
+        assert!(castle("e1g1", true).is_err());
+    }
+
+    #[test]
+    fn search_feature_options() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name NullMovePruning value false");
+        uci.handle("setoption name latemovereductions value false");
+        assert_eq!(uci.features, SearchFeatures {
+            null_move: false,
+            late_move_reductions: false,
+            ..SearchFeatures::default()
+        });
+
+        uci.handle("setoption name FutilityPruning value off");
+        assert!(uci.features.futility);
+        let output = String::from_utf8(uci.run("uci\n".as_bytes())).unwrap();
+        assert!(output.contains("info string invalid value 'off' for option FutilityPruning"));
+        assert!(output.contains("option name CheckExtensions type check default true"));
+    }
+
+    #[test]
+    fn go_with_a_clock_answers_in_time() {
+        let start = std::time::Instant::now();
+        let output = run("position startpos\ngo wtime 2000 btime 2000\n");
+        assert!(output.contains("bestmove "));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn go_reports_search_info() {
+        let output = run("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3");
+        assert!(output.contains("info depth 1 seldepth "));
+        assert!(output.contains(" score mate 1 "));
+        assert!(output.contains("bestmove a1a8"));
+    }
+
+    #[test]
+    fn bad_input_is_reported_not_fatal() {
+        let output = run("position startpos moves e2e5\nfoo\nsetoption name Foo value 1\nisready\n");
+        assert!(output.contains("info string invalid move e2e5"));
+        assert!(output.contains("info string unknown command 'foo'"));
+        assert!(output.contains("info string unknown option 'Foo'"));
+        assert!(output.ends_with("readyok\n"));
+    }
+}